@@ -1,8 +1,316 @@
-use std::io::{self};
+use std::{env, io::{self}, cmp::Reverse};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Shape {
+    Rock,
+    Paper,
+    Scissors
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win
+}
+
+const SHAPES: [Shape; 3] = [Shape::Rock, Shape::Paper, Shape::Scissors];
+const OUTCOMES: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
+const COLUMNS: [char; 3] = ['X', 'Y', 'Z'];
+
+impl Shape {
+    fn from_opponent(code: &str) -> Shape {
+        match code {
+            "A" => Shape::Rock,
+            "B" => Shape::Paper,
+            "C" => Shape::Scissors,
+            &_ => panic!("Help!")
+        }
+    }
+
+    fn score(self) -> u32 {
+        match self {
+            Shape::Rock => 1,
+            Shape::Paper => 2,
+            Shape::Scissors => 3
+        }
+    }
+
+    /// Shape this one defeats
+    fn beats(self) -> Shape {
+        match self {
+            Shape::Rock => Shape::Scissors,
+            Shape::Paper => Shape::Rock,
+            Shape::Scissors => Shape::Paper
+        }
+    }
+
+    /// Shape that defeats this one
+    fn beaten_by(self) -> Shape {
+        match self {
+            Shape::Rock => Shape::Paper,
+            Shape::Paper => Shape::Scissors,
+            Shape::Scissors => Shape::Rock
+        }
+    }
+
+    fn outcome_against(self, opponent: Shape) -> Outcome {
+        if self == opponent {
+            Outcome::Draw
+        }
+        else if self.beats() == opponent {
+            Outcome::Win
+        }
+        else {
+            Outcome::Lose
+        }
+    }
+}
+
+impl Outcome {
+    fn score(self) -> u32 {
+        match self {
+            Outcome::Lose => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6
+        }
+    }
+
+    /// Shape we must throw against the opponent to reach this outcome
+    fn shape_against(self, opponent: Shape) -> Shape {
+        match self {
+            Outcome::Lose => opponent.beats(),
+            Outcome::Draw => opponent,
+            Outcome::Win => opponent.beaten_by()
+        }
+    }
+}
+
+fn round_score(mine: Shape, opponent: Shape) -> u32 {
+    mine.score() + mine.outcome_against(opponent).score()
+}
+
+/// Opponent shape and X/Y/Z column index for a strategy guide line
+fn parse_round(line: &str) -> (Shape, usize) {
+    let mut parts = line.split_ascii_whitespace();
+    let opponent = Shape::from_opponent(parts.next().unwrap_or(""));
+    let column = match parts.next() {
+        Some("X") => 0,
+        Some("Y") => 1,
+        Some("Z") => 2,
+        _ => panic!("Help!")
+    };
+    (opponent, column)
+}
+
+/// All orderings of three items, i.e. every bijection from {X,Y,Z}
+fn permutations<T: Copy>(items: [T; 3]) -> Vec<[T; 3]> {
+    [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]]
+        .iter()
+        .map(|order| [items[order[0]], items[order[1]], items[order[2]]])
+        .collect()
+}
+
+/// One possible meaning of the X/Y/Z column
+#[derive(Clone, Copy, Debug)]
+enum Cipher {
+    Shapes([Shape; 3]),
+    Outcomes([Outcome; 3])
+}
+
+impl Cipher {
+    fn all() -> Vec<Cipher> {
+        let mut ciphers = permutations(SHAPES).into_iter().map(Cipher::Shapes).collect::<Vec<_>>();
+        ciphers.extend(permutations(OUTCOMES).into_iter().map(Cipher::Outcomes));
+        ciphers
+    }
+
+    fn decode(&self, opponent: Shape, column: usize) -> Shape {
+        match self {
+            Cipher::Shapes(shapes) => shapes[column],
+            Cipher::Outcomes(outcomes) => outcomes[column].shape_against(opponent)
+        }
+    }
+
+    fn total_score(&self, rounds: &[(Shape, usize)]) -> u32 {
+        rounds.iter().fold(0, |acc, &(opponent, column)| {
+            acc + round_score(self.decode(opponent, column), opponent)
+        })
+    }
+
+    fn describe(&self) -> String {
+        let meanings = match self {
+            Cipher::Shapes(shapes) => shapes.iter().map(|s| format!("{:?}", s)).collect::<Vec<_>>(),
+            Cipher::Outcomes(outcomes) => outcomes.iter().map(|o| format!("{:?}", o)).collect::<Vec<_>>()
+        };
+        COLUMNS.iter().zip(meanings).map(|(column, meaning)| format!("{}={}", column, meaning)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Score every interpretation of the X/Y/Z column, flagging the best or those matching `target`
+fn cipher_search(lines: &[String], target: Option<u32>) {
+    let rounds = lines.iter().filter(|line| !line.is_empty()).map(|line| parse_round(line)).collect::<Vec<_>>();
+
+    println!("Cipher search\r\n{}", "-".repeat(10));
+    let mut scored = Cipher::all().into_iter().map(|cipher| (cipher.total_score(&rounds), cipher)).collect::<Vec<_>>();
+    scored.sort_by_key(|s| Reverse(s.0));
+
+    let best_score = scored[0].0;
+    for (score, cipher) in scored.iter() {
+        let flag = match target {
+            Some(target_score) if *score == target_score => "  <- matches target",
+            None if *score == best_score => "  <- best",
+            _ => ""
+        };
+        println!("{:<40} {:>6}{}", cipher.describe(), score, flag);
+    }
+
+    if let Some(target_score) = target {
+        let matches = scored.iter().filter(|(score, _)| *score == target_score).count();
+        println!("\nInterpretations matching {}: {}", target_score, matches);
+    }
+}
+
+/// Small xorshift generator so tournament runs are reproducible from a seed
+struct Rng {
+    state: u64
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        // a zero state would only ever produce zeros
+        Rng { state: if state == 0 { 1 } else { state } }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+fn shape_index(shape: Shape) -> usize {
+    SHAPES.iter().position(|&s| s == shape).unwrap()
+}
+
+/// Opponent strategies, each of which only sees our previous throws
+#[derive(Clone, Copy, Debug)]
+enum OpponentModel {
+    Random,
+    Frequency,
+    Markov
+}
+
+const OPPONENT_MODELS: [OpponentModel; 3] = [OpponentModel::Random, OpponentModel::Frequency, OpponentModel::Markov];
+
+/// An opponent model plus what it has learned from our throws so far
+struct Opponent {
+    model: OpponentModel,
+    counts: [usize; 3],
+    transitions: [[usize; 3]; 3],
+    last: Option<Shape>
+}
+
+impl Opponent {
+    fn new(model: OpponentModel) -> Opponent {
+        Opponent { model, counts: [0; 3], transitions: [[0; 3]; 3], last: None }
+    }
+
+    /// Pick the opponent's next throw given our history so far
+    fn next_shape(&self, rng: &mut Rng) -> Shape {
+        let counts = match (self.model, self.last) {
+            (OpponentModel::Random, _) | (OpponentModel::Markov, None) => [0; 3],
+            (OpponentModel::Frequency, _) => self.counts,
+            (OpponentModel::Markov, Some(last)) => self.transitions[shape_index(last)]
+        };
+
+        // Predict our most likely throw (random among ties) and play what beats it
+        let most = *counts.iter().max().unwrap();
+        let candidates = (0..3).filter(|&idx| counts[idx] == most).collect::<Vec<_>>();
+        let predicted = SHAPES[candidates[rng.below(candidates.len())]];
+        predicted.beaten_by()
+    }
+
+    fn observe(&mut self, mine: Shape) {
+        self.counts[shape_index(mine)] += 1;
+        if let Some(last) = self.last {
+            self.transitions[shape_index(last)][shape_index(mine)] += 1;
+        }
+        self.last = Some(mine);
+    }
+}
+
+/// Play the guide's throws against a model, returning our total score
+fn play_match(our_shapes: &[Shape], model: OpponentModel, rng: &mut Rng) -> u32 {
+    let mut opponent = Opponent::new(model);
+    let mut total = 0;
+    for &mine in our_shapes {
+        total += round_score(mine, opponent.next_shape(rng));
+        opponent.observe(mine);
+    }
+    total
+}
+
+fn print_distribution(label: &str, scores: &mut [u32]) {
+    scores.sort_unstable();
+    let n = scores.len();
+    let mean = scores.iter().map(|&s| s as f64).sum::<f64>() / n as f64;
+    let variance = scores.iter().map(|&s| (s as f64 - mean).powi(2)).sum::<f64>() / n as f64;
+    println!("{:<14} mean {:>9.1}  stddev {:>7.1}  min {:>6}  p10 {:>6}  median {:>6}  p90 {:>6}  max {:>6}",
+        label, mean, variance.sqrt(), scores[0], scores[n / 10], scores[n / 2], scores[n * 9 / 10], scores[n - 1]);
+}
+
+/// Replay the guide against each opponent model over many seeded runs
+fn tournament(lines: &[String], runs: usize, seed: u64) {
+    let rounds = lines.iter().filter(|line| !line.is_empty()).map(|line| parse_round(line)).collect::<Vec<_>>();
+    let mut rng = Rng::new(seed);
+
+    println!("Tournament: {} rounds, {} runs, seed {}\r\n{}", rounds.len(), runs, seed, "-".repeat(10));
+    for (label, cipher) in [("Part 1 guide", Cipher::Shapes(SHAPES)), ("Part 2 guide", Cipher::Outcomes(OUTCOMES))] {
+        let our_shapes = rounds.iter().map(|&(opponent, column)| cipher.decode(opponent, column)).collect::<Vec<_>>();
+        println!("{} ({}), score against the recorded opponent moves: {}", label, cipher.describe(), cipher.total_score(&rounds));
+
+        for model in OPPONENT_MODELS {
+            let mut scores = (0..runs).map(|_| play_match(&our_shapes, model, &mut rng)).collect::<Vec<_>>();
+            print_distribution(format!("{:?}", model).as_str(), &mut scores);
+        }
+
+        // Baseline: throwing uniformly at random against the same opponents
+        for model in OPPONENT_MODELS {
+            let mut scores = (0..runs).map(|_| {
+                let random_shapes = (0..rounds.len()).map(|_| SHAPES[rng.below(3)]).collect::<Vec<_>>();
+                play_match(&random_shapes, model, &mut rng)
+            }).collect::<Vec<_>>();
+            print_distribution(format!("vs {:?}*", model).as_str(), &mut scores);
+        }
+        println!("* our throws chosen at random instead of from the guide\n");
+    }
+}
 
 fn main() {
     let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lines().flatten().collect();
+    let lines: Vec<String> = stdin.lines().map_while(Result::ok).collect();
+
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("cipher") {
+        let target = args.get(1).map(|t| t.parse::<u32>().expect("target score must be a number"));
+        cipher_search(&lines, target);
+        return;
+    }
+    if args.first().map(|s| s.as_str()) == Some("tournament") {
+        let runs = args.get(1).map(|r| r.parse::<usize>().expect("runs must be a number")).unwrap_or(100);
+        let seed = args.get(2).map(|s| s.parse::<u64>().expect("seed must be a number")).unwrap_or(2022);
+        tournament(&lines, runs.max(1), seed);
+        return;
+    }
 
     println!("Part 1\r\n{}", "-".repeat(10));
     let part_1_total_score = lines.iter().fold(0, | acc, round | {
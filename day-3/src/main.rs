@@ -1,34 +1,506 @@
-use std::io::{self};
+use std::{env, io::{self}};
+
+/// Sacks per elf group unless another size is given
+const GROUP_SIZE: usize = 3;
+
+/// Set of rucksack item types, one bit per priority (1-52)
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+struct ItemSet {
+    bits: u64
+}
+
+impl ItemSet {
+    /// Priority of an item type, a-z are 1-26 and A-Z are 27-52
+    fn priority(item: u8) -> Option<u32> {
+        match item {
+            b'a'..=b'z' => Some((item - b'`') as u32),
+            b'A'..=b'Z' => Some((item - b'&') as u32),
+            _ => None
+        }
+    }
+
+    fn item_for_priority(priority: u32) -> u8 {
+        if priority <= 26 {
+            b'`' + priority as u8
+        }
+        else {
+            b'&' + priority as u8
+        }
+    }
+
+    /// Build a set from rucksack contents, skipping anything that is not an item type
+    fn from_items(items: &[u8]) -> ItemSet {
+        items.iter().fold(ItemSet::default(), |mut set, &item| {
+            set.insert(item);
+            set
+        })
+    }
+
+    fn insert(&mut self, item: u8) -> bool {
+        match ItemSet::priority(item) {
+            Some(priority) => {
+                self.bits |= 1 << priority;
+                true
+            },
+            None => false
+        }
+    }
+
+    fn contains(&self, item: u8) -> bool {
+        match ItemSet::priority(item) {
+            Some(priority) => self.bits & (1 << priority) != 0,
+            None => false
+        }
+    }
+
+    fn union(&self, other: &ItemSet) -> ItemSet {
+        ItemSet { bits: self.bits | other.bits }
+    }
+
+    fn intersection(&self, other: &ItemSet) -> ItemSet {
+        ItemSet { bits: self.bits & other.bits }
+    }
+
+    #[allow(dead_code)]
+    fn difference(&self, other: &ItemSet) -> ItemSet {
+        ItemSet { bits: self.bits & !other.bits }
+    }
+
+    fn len(&self) -> usize {
+        self.bits.count_ones() as usize
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Priorities present in the set, lowest first
+    fn priorities(&self) -> impl Iterator<Item = u32> + '_ {
+        (1..=52).filter(move |priority| self.bits & (1 << priority) != 0)
+    }
+
+    /// Item types present in the set, in priority order
+    fn items(&self) -> impl Iterator<Item = u8> + '_ {
+        self.priorities().map(ItemSet::item_for_priority)
+    }
+
+    fn priority_sum(&self) -> u32 {
+        self.priorities().sum()
+    }
+}
+
+/// Item types shared by every sack in the group, for any group size
+fn common_items<S: AsRef<[u8]>>(sacks: &[S]) -> ItemSet {
+    match sacks.split_first() {
+        Some((first, rest)) => rest.iter().fold(ItemSet::from_items(first.as_ref()), |acc, sack| {
+            acc.intersection(&ItemSet::from_items(sack.as_ref()))
+        }),
+        None => ItemSet::default()
+    }
+}
+
+/// Problems found in a single rucksack line
+struct SackAudit {
+    line_number: usize,
+    length: usize,
+    invalid: Vec<(usize, char)>,
+    misplaced: ItemSet
+}
+
+impl SackAudit {
+    fn new(line_number: usize, sack: &str) -> SackAudit {
+        let invalid = sack.chars().enumerate().filter(|(_, c)| !c.is_ascii_alphabetic()).map(|(pos, c)| (pos + 1, c)).collect::<Vec<_>>();
+        let (left, right) = sack.as_bytes().split_at(sack.len() / 2);
+        SackAudit { line_number, length: sack.len(), invalid, misplaced: common_items(&[left, right]) }
+    }
+
+    fn is_clean(&self) -> bool {
+        self.length.is_multiple_of(2) && self.invalid.is_empty() && self.misplaced.len() == 1
+    }
+}
+
+fn describe_items(set: &ItemSet) -> String {
+    set.items().map(|item| format!("{} ({})", item as char, ItemSet::priority(item).unwrap())).collect::<Vec<_>>().join(", ")
+}
+
+/// Report every sack's misplaced items and every group's badge, with line numbers
+fn audit(lines: &[String], group_size: usize) {
+    println!("Sack audit\r\n{}", "-".repeat(10));
+    let audits = lines.iter().enumerate().map(|(idx, sack)| SackAudit::new(idx + 1, sack)).collect::<Vec<_>>();
+    for sack in audits.iter() {
+        let misplaced = match sack.misplaced.len() {
+            0 => "no misplaced items".to_string(),
+            1 => format!("misplaced {}", describe_items(&sack.misplaced)),
+            n => format!("{} misplaced types: {}", n, describe_items(&sack.misplaced))
+        };
+        let mut problems: Vec<String> = vec![];
+        if sack.length % 2 == 1 {
+            problems.push(format!("odd length {}", sack.length));
+        }
+        if !sack.invalid.is_empty() {
+            let chars = sack.invalid.iter().map(|(pos, c)| format!("{:?}@{}", c, pos)).collect::<Vec<_>>().join(" ");
+            problems.push(format!("invalid characters {}", chars));
+        }
+        let flag = if sack.is_clean() { "ok " } else { "!! " };
+        println!("{}line {:>4}: {}{}", flag, sack.line_number, misplaced, problems.iter().map(|p| format!("; {}", p)).collect::<String>());
+    }
+
+    let count = |f: &dyn Fn(&SackAudit) -> bool| audits.iter().filter(|a| f(a)).count();
+    println!("\nSacks: {}, clean: {}", audits.len(), count(&|a| a.is_clean()));
+    println!("No misplaced items: {}", count(&|a| a.misplaced.is_empty()));
+    println!("Multiple misplaced types: {}", count(&|a| a.misplaced.len() > 1));
+    println!("Odd length: {}", count(&|a| a.length % 2 == 1));
+    println!("Invalid characters: {}\n", count(&|a| !a.invalid.is_empty()));
+
+    println!("Group audit\r\n{}", "-".repeat(10));
+    let mut bad_groups = 0;
+    for (group_idx, group) in lines.chunks(group_size).enumerate() {
+        let first_line = group_idx * group_size + 1;
+        let last_line = first_line + group.len() - 1;
+        let badge = common_items(group);
+        let problem = if group.len() < group_size {
+            Some(format!("incomplete group of {}", group.len()))
+        }
+        else {
+            match badge.len() {
+                0 => Some("no badge".to_string()),
+                1 => None,
+                _ => Some(format!("ambiguous badge: {}", describe_items(&badge)))
+            }
+        };
+        match problem {
+            Some(problem) => {
+                bad_groups += 1;
+                println!("!! group {:>3} (lines {}-{}): {}", group_idx + 1, first_line, last_line, problem);
+            },
+            None => println!("ok group {:>3} (lines {}-{}): badge {}", group_idx + 1, first_line, last_line, describe_items(&badge))
+        }
+    }
+    println!("\nGroups with problems: {}", bad_groups);
+}
+
+/// Upper bound on backtracking steps before the grouping search gives up
+const GROUPING_SEARCH_LIMIT: usize = 10_000_000;
+
+enum GroupingResult {
+    Found(Vec<[usize; 3]>),
+    Impossible,
+    GaveUp
+}
+
+/// Backtracking search that partitions elves into triples sharing exactly one badge type
+struct GroupingSolver {
+    sacks: Vec<ItemSet>,
+    // for each elf, the partner pairs (j, k) with j, k > elf forming a valid group
+    triples: Vec<Vec<(usize, usize)>>,
+    used: Vec<bool>,
+    groups: Vec<[usize; 3]>,
+    steps: usize
+}
+
+impl GroupingSolver {
+    fn new(lines: &[String]) -> GroupingSolver {
+        let sacks = lines.iter().map(|line| ItemSet::from_items(line.as_bytes())).collect::<Vec<_>>();
+        let n = sacks.len();
+        let mut triples: Vec<Vec<(usize, usize)>> = vec![vec![]; n];
+        for i in 0..n {
+            for j in i + 1..n {
+                let pair = sacks[i].intersection(&sacks[j]);
+                if pair.is_empty() {
+                    continue;
+                }
+                let partners = (j + 1..n).filter(|&k| pair.intersection(&sacks[k]).len() == 1).map(|k| (j, k)).collect::<Vec<_>>();
+                triples[i].extend(partners);
+            }
+        }
+        GroupingSolver { sacks, triples, used: vec![false; n], groups: vec![], steps: 0 }
+    }
+
+    fn solve(mut self) -> GroupingResult {
+        if !self.sacks.len().is_multiple_of(3) {
+            return GroupingResult::Impossible;
+        }
+        match self.place() {
+            Some(true) => GroupingResult::Found(self.groups),
+            Some(false) => GroupingResult::Impossible,
+            None => GroupingResult::GaveUp
+        }
+    }
+
+    /// Group the lowest unassigned elf, None once the step budget runs out
+    fn place(&mut self) -> Option<bool> {
+        let elf = match self.used.iter().position(|&used| !used) {
+            Some(elf) => elf,
+            None => return Some(true)
+        };
+        self.used[elf] = true;
+        for idx in 0..self.triples[elf].len() {
+            let (j, k) = self.triples[elf][idx];
+            if self.used[j] || self.used[k] {
+                continue;
+            }
+            self.steps += 1;
+            if self.steps > GROUPING_SEARCH_LIMIT {
+                return None;
+            }
+            self.used[j] = true;
+            self.used[k] = true;
+            self.groups.push([elf, j, k]);
+            if self.place()? {
+                return Some(true);
+            }
+            self.groups.pop();
+            self.used[j] = false;
+            self.used[k] = false;
+        }
+        self.used[elf] = false;
+        Some(false)
+    }
+}
+
+/// Treat the rucksacks as shuffled and look for any valid grouping into threes
+fn regroup(lines: &[String]) {
+    let lines = lines.iter().filter(|line| !line.is_empty()).cloned().collect::<Vec<_>>();
+
+    println!("Badge grouping\r\n{}", "-".repeat(10));
+    match GroupingSolver::new(&lines).solve() {
+        GroupingResult::Found(groups) => {
+            println!("Valid grouping found for {} elves\n", lines.len());
+            let mut badge_sum = 0;
+            for (group_idx, group) in groups.iter().enumerate() {
+                let badge = common_items(&group.iter().map(|&elf| lines[elf].as_bytes()).collect::<Vec<_>>());
+                badge_sum += badge.priority_sum();
+                println!("group {:>3}: lines {:>4} {:>4} {:>4}  badge {}", group_idx + 1, group[0] + 1, group[1] + 1, group[2] + 1, describe_items(&badge));
+            }
+            println!("\nGroups priority score sum: {}", badge_sum);
+        },
+        GroupingResult::Impossible => println!("No valid grouping exists for {} elves", lines.len()),
+        GroupingResult::GaveUp => println!("Search gave up after {} steps without finding a grouping", GROUPING_SEARCH_LIMIT)
+    }
+}
+
+/// A rucksack split into its two compartments
+#[derive(Clone)]
+struct Sack {
+    compartments: [Vec<u8>; 2]
+}
+
+impl Sack {
+    fn new(line: &str) -> Sack {
+        let (left, right) = line.as_bytes().split_at(line.len() / 2);
+        Sack { compartments: [left.to_vec(), right.to_vec()] }
+    }
+
+    fn to_line(&self) -> String {
+        self.compartments.concat().iter().map(|&item| item as char).collect()
+    }
+
+    fn misplaced(&self) -> ItemSet {
+        common_items(&self.compartments)
+    }
+
+    /// Fewest swaps (left position, right position) leaving no item type in both compartments
+    fn plan_swaps(&self) -> Option<Vec<(usize, usize)>> {
+        let [left, right] = &self.compartments;
+        if left.len() != right.len() {
+            return None;
+        }
+        let types = ItemSet::from_items(left).union(&ItemSet::from_items(right)).items().collect::<Vec<_>>();
+        let counts = types.iter().map(|&item| {
+            (left.iter().filter(|&&i| i == item).count(), right.iter().filter(|&&i| i == item).count())
+        }).collect::<Vec<_>>();
+
+        // costs[t][balance] is the fewest items moved left to right after deciding the first t types,
+        // where balance is items moved left to right minus right to left, offset by the compartment size
+        let offset = left.len();
+        let width = 2 * offset + 1;
+        let mut costs: Vec<Vec<Option<usize>>> = vec![vec![None; width]; types.len() + 1];
+        costs[0][offset] = Some(0);
+        for (t, &(l, r)) in counts.iter().enumerate() {
+            for balance in 0..width {
+                let cost = match costs[t][balance] {
+                    Some(cost) => cost,
+                    None => continue
+                };
+                // keep the type in the left compartment, pulling its right items over
+                if balance >= r {
+                    let next = &mut costs[t + 1][balance - r];
+                    *next = Some(next.map_or(cost, |c| c.min(cost)));
+                }
+                // or push it entirely into the right compartment
+                if balance + l < width {
+                    let next = &mut costs[t + 1][balance + l];
+                    *next = Some(next.map_or(cost + l, |c| c.min(cost + l)));
+                }
+            }
+        }
+        costs[types.len()][offset]?;
+
+        // Walk back through the table to recover which side each type ended up on
+        let mut to_right = vec![false; types.len()];
+        let mut balance = offset;
+        for t in (0..types.len()).rev() {
+            let (l, r) = counts[t];
+            let cost = costs[t + 1][balance].unwrap();
+            if balance + r < width && costs[t][balance + r] == Some(cost) {
+                balance += r;
+            }
+            else {
+                to_right[t] = true;
+                balance -= l;
+            }
+        }
+
+        let right_types = types.iter().zip(to_right).filter(|&(_, to_right)| to_right).fold(ItemSet::default(), |mut set, (&item, _)| {
+            set.insert(item);
+            set
+        });
+        let out_of_left = (0..left.len()).filter(|&pos| right_types.contains(left[pos]));
+        let out_of_right = (0..right.len()).filter(|&pos| !right_types.contains(right[pos]));
+        Some(out_of_left.zip(out_of_right).collect())
+    }
+
+    fn apply_swaps(&mut self, swaps: &[(usize, usize)]) {
+        let [left, right] = &mut self.compartments;
+        for &(l, r) in swaps {
+            std::mem::swap(&mut left[l], &mut right[r]);
+        }
+    }
+}
+
+/// Item exchange between two sacks: partner sack, our compartment and position, their compartment and position
+type CrossSwap = (usize, usize, usize, usize, usize);
+
+/// Cheapest single item exchange with another sack in the group that makes both sacks fixable
+fn plan_cross_swap(group: &[Sack], sack: usize) -> Option<CrossSwap> {
+    let mut best: Option<(usize, CrossSwap)> = None;
+    for partner in (0..group.len()).filter(|&p| p != sack) {
+        for from in 0..2 {
+            for to in 0..2 {
+                let ours = &group[sack].compartments[from];
+                let theirs = &group[partner].compartments[to];
+                // one position per item type is enough, the rest are equivalent
+                let our_positions = ItemSet::from_items(ours).items().map(|item| ours.iter().position(|&i| i == item).unwrap()).collect::<Vec<_>>();
+                let their_positions = ItemSet::from_items(theirs).items().map(|item| theirs.iter().position(|&i| i == item).unwrap()).collect::<Vec<_>>();
+                for &ours_pos in our_positions.iter() {
+                    for &theirs_pos in their_positions.iter() {
+                        let mut a = group[sack].clone();
+                        let mut b = group[partner].clone();
+                        std::mem::swap(&mut a.compartments[from][ours_pos], &mut b.compartments[to][theirs_pos]);
+                        if let (Some(a_swaps), Some(b_swaps)) = (a.plan_swaps(), b.plan_swaps()) {
+                            let cost = 1 + a_swaps.len() + b_swaps.len();
+                            if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                                best = Some((cost, (partner, from, ours_pos, to, theirs_pos)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    best.map(|(_, exchange)| exchange)
+}
+
+/// Fix every sack so no item type sits in both compartments, trading between sacks of a group of the given size first,
+/// writing the repacked lines to stdout
+fn repack(lines: &[String], across_groups: Option<usize>) {
+    let mut sacks = lines.iter().map(|line| Sack::new(line)).collect::<Vec<_>>();
+    let mut total_swaps = 0;
+    let mut unfixable = 0;
+
+    eprintln!("Repacking plan\r\n{}", "-".repeat(10));
+    if let Some(group_size) = across_groups {
+        for (group_idx, group) in sacks.chunks_mut(group_size).enumerate() {
+            for sack in 0..group.len() {
+                if group[sack].plan_swaps().is_some() {
+                    continue;
+                }
+                if let Some((partner, from, ours_pos, to, theirs_pos)) = plan_cross_swap(group, sack) {
+                    let item = group[sack].compartments[from][ours_pos];
+                    let other = group[partner].compartments[to][theirs_pos];
+                    let (a, b) = if sack < partner {
+                        let (head, tail) = group.split_at_mut(partner);
+                        (&mut head[sack], &mut tail[0])
+                    }
+                    else {
+                        let (head, tail) = group.split_at_mut(sack);
+                        (&mut tail[0], &mut head[partner])
+                    };
+                    std::mem::swap(&mut a.compartments[from][ours_pos], &mut b.compartments[to][theirs_pos]);
+                    total_swaps += 1;
+                    let line_of = |idx: usize| group_idx * group_size + idx + 1;
+                    eprintln!("line {:>4}: exchanged {} with {} from line {}", line_of(sack), item as char, other as char, line_of(partner));
+                }
+            }
+        }
+    }
+
+    for (idx, sack) in sacks.iter_mut().enumerate() {
+        match sack.plan_swaps() {
+            Some(swaps) => {
+                let misplaced = sack.misplaced();
+                sack.apply_swaps(&swaps);
+                total_swaps += swaps.len();
+                if !swaps.is_empty() {
+                    eprintln!("line {:>4}: {} swaps to separate {}", idx + 1, swaps.len(), describe_items(&misplaced));
+                }
+            },
+            None => {
+                unfixable += 1;
+                eprintln!("line {:>4}: cannot be split into two equal compartments without shared items", idx + 1);
+            }
+        }
+    }
+    eprintln!("\nTotal swaps: {}, unfixable sacks: {}\n", total_swaps, unfixable);
+
+    for sack in sacks.iter() {
+        println!("{}", sack.to_line());
+    }
+}
+
+/// Group size from an optional argument, `GROUP_SIZE` when it is missing
+fn group_size(arg: Option<String>) -> usize {
+    match arg {
+        Some(arg) => arg.parse::<usize>().ok().filter(|&size| size > 0).expect("group size must be a positive number"),
+        None => GROUP_SIZE
+    }
+}
 
 fn main() {
 
     let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lines().flatten().collect();
+    let lines: Vec<String> = stdin.lines().map_while(Result::ok).collect();
+
+    match env::args().nth(1).as_deref() {
+        Some("audit") => {
+            audit(&lines, group_size(env::args().nth(2)));
+            return;
+        },
+        Some("regroup") => {
+            regroup(&lines);
+            return;
+        },
+        Some("repack") => {
+            let across_groups = match env::args().nth(2).as_deref() {
+                Some("group") => Some(group_size(env::args().nth(3))),
+                _ => None
+            };
+            repack(&lines, across_groups);
+            return;
+        },
+        _ => ()
+    }
 
     println!("Part 1\r\n{}", "-".repeat(10));
     let part_1_priority_sum = lines.iter().fold(0, | acc, sack | {
-        let compartments: Vec<&[u8]> = sack.as_bytes().chunks(sack.len() / 2).collect();
-        let mut dup = *(compartments[0].iter().filter(|&item| compartments[1].contains(&item) == true).nth(0).unwrap());
-        if dup >= b'a' {
-            dup = dup - b'`'; // 1-26
-        }
-        else { 
-            dup = dup - b'&'; // 27-52
-        }
-        acc + dup as u32
+        let (left, right) = sack.as_bytes().split_at(sack.len() / 2);
+        acc + common_items(&[left, right]).priority_sum()
     });
     println!("Priority score sum: {}\n", part_1_priority_sum);
 
     println!("Part 2\r\n{}", "-".repeat(10));
-    let part_2_priority_sum = lines.chunks(3).fold(0, | acc, group| {
-        let mut dup = *(group[0].as_bytes().iter().filter(|&a| group[1].as_bytes().contains(&a) && group[2].as_bytes().contains(&a)).nth(0).unwrap());
-        if dup >= b'a' {
-            dup = dup - b'`'; // 1-26
-        }
-        else { 
-            dup = dup - b'&'; // 27-52
-        }
-        acc + dup as u32
+    let part_2_priority_sum = lines.chunks(group_size(env::args().nth(1))).fold(0, | acc, group| {
+        acc + common_items(group).priority_sum()
     });
     println!("Groups priority score sum: {}\n", part_2_priority_sum);
 }
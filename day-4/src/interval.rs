@@ -0,0 +1,207 @@
+/// Closed range [start, end] over any ordered type
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T
+}
+
+impl<T: Ord + Copy> Interval<T> {
+    /// Build an interval, swapping the bounds if they are given backwards
+    pub fn new(start: T, end: T) -> Self {
+        if start <= end {
+            Interval { start, end }
+        }
+        else {
+            Interval { start: end, end: start }
+        }
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    /// Other interval lies entirely within this one
+    pub fn contains_interval(&self, other: &Interval<T>) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        if self.overlaps(other) {
+            Some(Interval { start: self.start.max(other.start), end: self.end.min(other.end) })
+        }
+        else {
+            None
+        }
+    }
+
+    /// Single interval covering both, only when they overlap
+    pub fn union(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        if self.overlaps(other) {
+            Some(self.hull(other))
+        }
+        else {
+            None
+        }
+    }
+
+    /// Smallest interval covering both, including any gap between them
+    pub fn hull(&self, other: &Interval<T>) -> Interval<T> {
+        Interval { start: self.start.min(other.start), end: self.end.max(other.end) }
+    }
+
+    /// Exclusive bounds of the space between two disjoint intervals
+    pub fn gap(&self, other: &Interval<T>) -> Option<(T, T)> {
+        if self.overlaps(other) {
+            None
+        }
+        else if self.end < other.start {
+            Some((self.end, other.start))
+        }
+        else {
+            Some((other.end, self.start))
+        }
+    }
+}
+
+/// Sorted set of disjoint intervals, overlapping inserts are merged.
+/// Ranges that only touch stay separate: with integers `1-3` and `4-6` are two ranges, since nothing
+/// here knows that no value lies between 3 and 4. Callers that want runs of whole numbers join them afterwards.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct IntervalSet<T> {
+    ranges: Vec<Interval<T>>
+}
+
+impl<T: Ord + Copy> IntervalSet<T> {
+    pub fn new() -> Self {
+        IntervalSet { ranges: vec![] }
+    }
+
+    /// Add an interval, merging it with every range it overlaps
+    pub fn insert(&mut self, interval: Interval<T>) {
+        // first range that could touch the new one
+        let first = self.ranges.partition_point(|r| r.end < interval.start);
+        let mut merged = interval;
+        let mut last = first;
+        while last < self.ranges.len() && self.ranges[last].overlaps(&merged) {
+            merged = merged.hull(&self.ranges[last]);
+            last += 1;
+        }
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end < value);
+        idx < self.ranges.len() && self.ranges[idx].contains(value)
+    }
+
+    /// Interval lies entirely within one of the ranges
+    pub fn contains_interval(&self, interval: &Interval<T>) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end < interval.start);
+        idx < self.ranges.len() && self.ranges[idx].contains_interval(interval)
+    }
+
+    pub fn overlaps(&self, interval: &Interval<T>) -> bool {
+        let idx = self.ranges.partition_point(|r| r.end < interval.start);
+        idx < self.ranges.len() && self.ranges[idx].overlaps(interval)
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut set = self.clone();
+        for &interval in other.iter() {
+            set.insert(interval);
+        }
+        set
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut set = IntervalSet::new();
+        let (mut a, mut b) = (0, 0);
+        while a < self.ranges.len() && b < other.ranges.len() {
+            if let Some(common) = self.ranges[a].intersection(&other.ranges[b]) {
+                set.ranges.push(common);
+            }
+            if self.ranges[a].end < other.ranges[b].end {
+                a += 1;
+            }
+            else {
+                b += 1;
+            }
+        }
+        set
+    }
+
+    /// Exclusive bounds of each space between consecutive ranges
+    pub fn gaps(&self) -> Vec<(T, T)> {
+        self.ranges.windows(2).map(|pair| (pair[0].end, pair[1].start)).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.ranges.iter()
+    }
+
+    /// Number of disjoint ranges
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+impl<T: Ord + Copy> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut set = IntervalSet::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(u32, u32)]) -> IntervalSet<u32> {
+        ranges.iter().map(|&(start, end)| Interval::new(start, end)).collect()
+    }
+
+    fn pairs(set: &IntervalSet<u32>) -> Vec<(u32, u32)> {
+        set.iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn insert_merges_overlapping_ranges() {
+        assert_eq!(pairs(&set(&[(5, 8), (1, 2), (7, 10)])), [(1, 2), (5, 10)]);
+        // one insert spanning several ranges swallows them all
+        assert_eq!(pairs(&set(&[(1, 2), (4, 5), (7, 8), (2, 7)])), [(1, 8)]);
+        assert_eq!(pairs(&set(&[(3, 6), (4, 5)])), [(3, 6)]);
+    }
+
+    #[test]
+    fn insert_keeps_touching_ranges_apart() {
+        assert_eq!(pairs(&set(&[(4, 6), (1, 3)])), [(1, 3), (4, 6)]);
+        // sharing an end point is an overlap
+        assert_eq!(pairs(&set(&[(1, 3), (3, 6)])), [(1, 6)]);
+    }
+
+    #[test]
+    fn intersection_of_sets() {
+        let a = set(&[(1, 5), (8, 12), (20, 30)]);
+        let b = set(&[(3, 9), (11, 25)]);
+        assert_eq!(pairs(&a.intersection(&b)), [(3, 5), (8, 9), (11, 12), (20, 25)]);
+        assert!(a.intersection(&set(&[(6, 7), (13, 19)])).is_empty());
+    }
+
+    #[test]
+    fn gaps_between_ranges() {
+        assert_eq!(set(&[(1, 3), (6, 7), (8, 9)]).gaps(), [(3, 6), (7, 8)]);
+        assert!(set(&[(1, 3)]).gaps().is_empty());
+        assert!(IntervalSet::<u32>::new().gaps().is_empty());
+    }
+}
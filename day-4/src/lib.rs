@@ -0,0 +1,2 @@
+//! Range helpers for the camp cleanup, also usable from other days through a path dependency on `day-4`
+pub mod interval;
@@ -1,48 +1,308 @@
-use std::io::{self};
+use std::{env, io::{self}, collections::BTreeMap};
 
-fn assignments_from_line(line: &String) -> ((u32,u32),(u32,u32)) {
-    let mut assignments = line.split(",");
-    let mut left = assignments.next().unwrap().split("-");
-    let mut right = assignments.next().unwrap().split("-");
-    
-    return ((left.next().unwrap().parse::<u32>().unwrap(),left.next().unwrap().parse::<u32>().unwrap()),(right.next().unwrap().parse::<u32>().unwrap(),right.next().unwrap().parse::<u32>().unwrap()));
+
+use day_4::interval::{Interval, IntervalSet};
+
+fn assignments_from_line(line: &str) -> (Interval<u32>, Interval<u32>) {
+    let mut assignments = line.split(",").map(|assignment| {
+        let mut sections = assignment.split("-").map(|section| section.parse::<u32>().unwrap());
+        Interval::new(sections.next().unwrap(), sections.next().unwrap())
+    });
+
+    (assignments.next().unwrap(), assignments.next().unwrap())
+}
+
+fn has_overlap(assignment: (Interval<u32>, Interval<u32>)) -> bool {
+    let (left, right) = assignment;
+    left.overlaps(&right)
+}
+
+fn has_full_overlap(assignment: (Interval<u32>, Interval<u32>)) -> bool {
+    let (left, right) = assignment;
+    left.contains_interval(&right) || right.contains_interval(&left)
+}
+
+/// Every elf's assignment in the camp, tagged with its line number and side of the pair
+fn camp_assignments(lines: &[String]) -> Vec<(usize, char, Interval<u32>)> {
+    lines.iter().enumerate().filter(|(_, line)| !line.is_empty()).flat_map(|(idx, line)| {
+        let (left, right) = assignments_from_line(line);
+        [(idx + 1, 'L', left), (idx + 1, 'R', right)]
+    }).collect()
+}
+
+/// Sweep across all assignments, returning runs of sections with the same number of elves
+fn coverage_runs(assignments: &[(usize, char, Interval<u32>)]) -> Vec<(Interval<u32>, usize)> {
+    let mut events: BTreeMap<u32, i64> = BTreeMap::new();
+    for (_, _, assignment) in assignments {
+        *events.entry(assignment.start).or_insert(0) += 1;
+        *events.entry(assignment.end + 1).or_insert(0) -= 1;
+    }
+
+    let mut runs = vec![];
+    let mut elves: i64 = 0;
+    let mut positions = events.iter().peekable();
+    while let Some((&section, &delta)) = positions.next() {
+        elves += delta;
+        if let Some((&next_section, _)) = positions.peek() {
+            runs.push((Interval::new(section, next_section - 1), elves as usize));
+        }
+    }
+    runs
+}
+
+/// Assignments lying entirely inside some other elf's assignment
+fn redundant_assignments(assignments: &[(usize, char, Interval<u32>)]) -> Vec<(usize, char, Interval<u32>, usize, char)> {
+    // widest first for equal starts, so any container comes before what it contains
+    let mut sorted = assignments.to_vec();
+    sorted.sort_by_key(|&(line, _, a)| (a.start, std::cmp::Reverse(a.end), line));
+
+    let mut redundant = vec![];
+    let mut widest: Option<(usize, char, Interval<u32>)> = None;
+    for (idx, &(line, side, assignment)) in sorted.iter().enumerate() {
+        let container = match widest {
+            Some(w) if w.2.end >= assignment.end => Some(w),
+            // an identical range later in the order also contains this one
+            _ => sorted.get(idx + 1).filter(|next| next.2 == assignment).copied()
+        };
+        if let Some((container_line, container_side, _)) = container {
+            redundant.push((line, side, assignment, container_line, container_side));
+        }
+        if widest.is_none_or(|w| assignment.end > w.2.end) {
+            widest = Some((line, side, assignment));
+        }
+    }
+    redundant.sort_by_key(|r| (r.0, r.1));
+    redundant
+}
+
+fn describe_sections(interval: &Interval<u32>) -> String {
+    if interval.start == interval.end {
+        format!("{}", interval.start)
+    }
+    else {
+        format!("{}-{}", interval.start, interval.end)
+    }
+}
+
+/// Camp-wide analysis of how every section is covered by all elves
+fn coverage(lines: &[String]) {
+    let assignments = camp_assignments(lines);
+    if assignments.is_empty() {
+        println!("No assignments");
+        return;
+    }
+    let runs = coverage_runs(&assignments);
+
+    println!("Maximum coverage\r\n{}", "-".repeat(10));
+    let max_elves = runs.iter().map(|&(_, elves)| elves).max().unwrap();
+    let busiest = runs.iter().filter(|&&(_, elves)| elves == max_elves).map(|(sections, _)| describe_sections(sections)).collect::<Vec<_>>();
+    println!("Most elves assigned to one section: {}", max_elves);
+    println!("Sections: {}\n", busiest.join(", "));
+
+    println!("Uncovered sections\r\n{}", "-".repeat(10));
+    let covered = assignments.iter().map(|&(_, _, a)| a).collect::<IntervalSet<u32>>();
+    let mut uncovered = covered.gaps().into_iter().filter(|&(before, after)| after > before + 1).map(|(before, after)| Interval::new(before + 1, after - 1)).collect::<Vec<_>>();
+    let first_covered = covered.iter().next().unwrap().start;
+    if first_covered > 1 {
+        uncovered.insert(0, Interval::new(1, first_covered - 1));
+    }
+    if uncovered.is_empty() {
+        println!("Every section from 1 to {} is covered\n", covered.iter().last().unwrap().end);
+    }
+    else {
+        let count = uncovered.iter().map(section_count).sum::<u32>();
+        println!("{} sections: {}\n", count, uncovered.iter().map(describe_sections).collect::<Vec<_>>().join(", "));
+    }
+
+    println!("Redundant assignments\r\n{}", "-".repeat(10));
+    let redundant = redundant_assignments(&assignments);
+    for (line, side, assignment, container_line, container_side) in redundant.iter() {
+        println!("line {:>4} {}: {} inside line {} {}", line, side, describe_sections(assignment), container_line, container_side);
+    }
+    println!("Fully redundant assignments: {} of {}\n", redundant.len(), assignments.len());
+
+    println!("Coverage histogram\r\n{}", "-".repeat(10));
+    for (sections, elves) in runs.iter() {
+        let bar = "#".repeat(((*elves as f64 / max_elves as f64) * 50.0).ceil() as usize);
+        for section in sections.start..=sections.end {
+            println!("section {:>4}: {:>5} {}", section, elves, bar);
+        }
+    }
+}
+
+fn section_count(interval: &Interval<u32>) -> u32 {
+    interval.end - interval.start + 1
+}
+
+/// Ranges of the set with touching ones joined, `1-3` and `4-6` are a single run of sections
+fn section_runs(required: &IntervalSet<u32>) -> Vec<Interval<u32>> {
+    let mut runs: Vec<Interval<u32>> = vec![];
+    for &range in required.iter() {
+        match runs.last_mut() {
+            Some(last) if last.end + 1 == range.start => last.end = range.end,
+            _ => runs.push(range)
+        }
+    }
+    runs
 }
 
-fn has_overlap(assignment: ((u32,u32), (u32,u32))) -> bool {
-    let ((a,b),(c,d)) = assignment;
+/// Split the required ranges into at most `elves` non-overlapping intervals with the smallest possible maximum workload
+fn balanced_assignments(required: &IntervalSet<u32>, elves: usize) -> Vec<Interval<u32>> {
+    let required = section_runs(required);
+    let elves_needed = |load: u32| required.iter().map(|r| section_count(r).div_ceil(load) as usize).sum::<usize>();
 
-    if (a >= c && a <= d || b <= d && b >= c) ||
-    (c >= a && c <= b || d <= b && d >= a) {
-        return true;
+    // binary search the smallest workload the available elves can cover
+    let (mut low, mut high) = (1, required.iter().map(section_count).max().unwrap_or(1));
+    while low < high {
+        let mid = (low + high) / 2;
+        if elves_needed(mid) <= elves {
+            high = mid;
+        }
+        else {
+            low = mid + 1;
+        }
     }
-    else
-    {
-        return false;
+
+    let mut assignments = vec![];
+    for range in required.iter() {
+        // spread this range as evenly as possible over the elves it needs
+        let len = section_count(range);
+        let shares = len.div_ceil(low);
+        let mut start = range.start;
+        for share in 0..shares {
+            let size = len / shares + if share < len % shares { 1 } else { 0 };
+            assignments.push(Interval::new(start, start + size - 1));
+            start += size;
+        }
     }
+    assignments
 }
 
-fn has_full_overlap(assignment: ((u32,u32), (u32,u32))) -> bool {
-    let ((a,b),(c,d)) = assignment;
+/// Reassign the camp so every required section is cleaned once, comparing against the original assignments
+fn optimize(lines: &[String]) {
+    let assignments = camp_assignments(lines);
+    let required = assignments.iter().map(|&(_, _, a)| a).collect::<IntervalSet<u32>>();
+    let required_sections = required.iter().map(section_count).sum::<u32>();
+    let runs = section_runs(&required).len();
+    let optimal = balanced_assignments(&required, assignments.len());
+
+    // hand out the largest overlaps first, so as few elves as possible change what they clean
+    let mut pairs = vec![];
+    for (slot, interval) in optimal.iter().enumerate() {
+        for (elf, (_, _, original)) in assignments.iter().enumerate() {
+            if let Some(common) = original.intersection(interval) {
+                pairs.push((section_count(&common), slot, elf));
+            }
+        }
+    }
+    pairs.sort_by_key(|&(overlap, slot, elf)| (std::cmp::Reverse(overlap), slot, elf));
 
-    if (a >= c && a <= d && b <= d && b >= c) ||
-       (c >= a && c <= b && d <= b && d >= a) {
-        return true;
+    let mut reassigned: Vec<Option<Interval<u32>>> = vec![None; assignments.len()];
+    let mut placed = vec![false; optimal.len()];
+    for (_, slot, elf) in pairs {
+        if !placed[slot] && reassigned[elf].is_none() {
+            reassigned[elf] = Some(optimal[slot]);
+            placed[slot] = true;
+        }
+    }
+    let mut idle_elves = (0..assignments.len()).filter(|&elf| reassigned[elf].is_none()).collect::<Vec<_>>().into_iter();
+    for slot in (0..optimal.len()).filter(|&slot| !placed[slot]) {
+        reassigned[idle_elves.next().unwrap()] = Some(optimal[slot]);
     }
-    else
-    {
-        return false;
+
+    println!("Reassignment\r\n{}", "-".repeat(10));
+    let mut changed_elves = 0;
+    let mut moved_sections = 0;
+    for (elf, &(line, side, original)) in assignments.iter().enumerate() {
+        let new_sections = reassigned[elf].map_or("idle".to_string(), |r| describe_sections(&r));
+        let kept = reassigned[elf].and_then(|r| r.intersection(&original)).map_or(0, |common| section_count(&common));
+        let dropped = section_count(&original) - kept;
+        let added = reassigned[elf].map_or(0, |r| section_count(&r)) - kept;
+        if reassigned[elf] != Some(original) {
+            changed_elves += 1;
+        }
+        moved_sections += dropped + added;
+        println!("line {:>4} {}: {:>7} -> {}", line, side, describe_sections(&original), new_sections);
     }
+
+    let original_work = assignments.iter().map(|(_, _, a)| section_count(a)).sum::<u32>();
+    let overlap = coverage_runs(&assignments).iter().filter(|&&(_, elves)| elves > 1).map(|(sections, elves)| section_count(sections) * (*elves as u32 - 1)).sum::<u32>();
+    let original_max = assignments.iter().map(|(_, _, a)| section_count(a)).max().unwrap_or(0);
+    let optimal_max = optimal.iter().map(section_count).max().unwrap_or(0);
+    let optimal_min = if optimal.len() < assignments.len() { 0 } else { optimal.iter().map(section_count).min().unwrap_or(0) };
+
+    println!("\nDistance from optimum\r\n{}", "-".repeat(10));
+    println!("Required sections: {} in {} ranges, elves: {}", required_sections, runs, assignments.len());
+    println!("Sections cleaned: {} originally, {} optimally", original_work, required_sections);
+    println!("Duplicated section cleanings: {}", overlap);
+    println!("Workload per elf: max {} originally, optimal {}-{}", original_max, optimal_min, optimal_max);
+    println!("Elves with a changed assignment: {}", changed_elves);
+    println!("Sections added or dropped across all elves: {}", moved_sections);
 }
 
 fn main() {
     let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lines().flatten().collect();
+    let lines: Vec<String> = stdin.lines().map_while(Result::ok).collect();
+
+    match env::args().nth(1).as_deref() {
+        Some("coverage") => {
+            coverage(&lines);
+            return;
+        },
+        Some("optimize") => {
+            optimize(&lines);
+            return;
+        },
+        _ => ()
+    }
 
     println!("Part 1\r\n{}", "-".repeat(10));
-    let part_1_fully_contained = lines.iter().filter(|line| has_full_overlap(assignments_from_line(&line))).count();
+    let part_1_fully_contained = lines.iter().filter(|line| has_full_overlap(assignments_from_line(line))).count();
     println!("Number fully contained: {}\n", part_1_fully_contained);
 
     println!("Part 2\r\n{}", "-".repeat(10));
-    let part_1_fully_contained = lines.iter().filter(|line| has_overlap(assignments_from_line(&line))).count();
+    let part_1_fully_contained = lines.iter().filter(|line| has_overlap(assignments_from_line(line))).count();
     println!("Number overlap: {}\n", part_1_fully_contained);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ranges: &[(u32, u32)]) -> IntervalSet<u32> {
+        ranges.iter().map(|&(start, end)| Interval::new(start, end)).collect()
+    }
+
+    fn pairs(intervals: &[Interval<u32>]) -> Vec<(u32, u32)> {
+        intervals.iter().map(|r| (r.start, r.end)).collect()
+    }
+
+    #[test]
+    fn section_runs_join_touching_ranges() {
+        assert_eq!(pairs(&section_runs(&set(&[(1, 3), (4, 6), (8, 9)]))), [(1, 6), (8, 9)]);
+        assert_eq!(pairs(&section_runs(&set(&[(7, 7), (1, 3), (4, 6)]))), [(1, 7)]);
+        assert!(section_runs(&set(&[])).is_empty());
+    }
+
+    #[test]
+    fn single_run_splits_into_equal_shares() {
+        for (sections, elves) in [(7, 4), (99, 2000), (10, 3), (12, 4), (1, 1)] {
+            let assignments = balanced_assignments(&set(&[(1, sections)]), elves);
+            let max = assignments.iter().map(section_count).max().unwrap();
+            assert_eq!(max, sections.div_ceil(elves as u32), "{} sections over {} elves", sections, elves);
+            assert_eq!(section_runs(&assignments.iter().copied().collect()), [Interval::new(1, sections)]);
+            assert!(assignments.len() <= elves);
+        }
+    }
+
+    #[test]
+    fn touching_assignments_are_balanced_as_one_run() {
+        assert_eq!(pairs(&balanced_assignments(&set(&[(1, 3), (4, 6), (7, 7)]), 4)), [(1, 2), (3, 4), (5, 6), (7, 7)]);
+    }
+
+    #[test]
+    fn separate_runs_split_unevenly() {
+        // 5 and 2 sections over 3 elves: the longer run needs two elves, so the best workload is 3
+        assert_eq!(pairs(&balanced_assignments(&set(&[(1, 5), (10, 11)]), 3)), [(1, 3), (4, 5), (10, 11)]);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{Move, Stacks};
+
+/// A machine that can carry out a rearrangement procedure move by move
+pub trait Crane {
+    fn name(&self) -> String;
+
+    /// Crates the source stack must hold for this crane to carry out the move
+    fn reach(&self, cmd: &Move) -> usize {
+        cmd.count
+    }
+
+    /// Carry out a single move, which must be valid for the current stacks
+    fn run_move(&self, stacks: &mut Stacks, cmd: &Move);
+
+    /// The move cut down to the crates actually available, None if it names a missing stack
+    fn clamp(&self, stacks: &Stacks, cmd: &Move) -> Option<Move> {
+        if cmd.from >= stacks.len() || cmd.to >= stacks.len() || cmd.from == cmd.to {
+            return None;
+        }
+        let available = stacks[cmd.from].len().saturating_sub(self.reach(cmd) - cmd.count);
+        Some(Move { count: cmd.count.min(available), ..*cmd })
+    }
+
+    /// Whether the move can be carried out on the current stacks
+    fn can_run(&self, stacks: &Stacks, cmd: &Move) -> bool {
+        cmd.from < stacks.len() && cmd.to < stacks.len() && cmd.from != cmd.to && stacks[cmd.from].len() >= self.reach(cmd)
+    }
+}
+
+/// Lift `count` crates sitting under the top `skip` crates of one stack and set them down on another,
+/// reversing the top `reverse_top` crates of the lifted block
+fn lift(stacks: &mut Stacks, cmd: &Move, count: usize, skip: usize, reverse_top: usize) {
+    let from = &mut stacks[cmd.from];
+    let above = from.len() - skip;
+    let mut block = from.drain(above - count..above).collect::<Vec<_>>();
+    let split = count - reverse_top.min(count);
+    block[split..].reverse();
+    stacks[cmd.to].extend(block);
+}
+
+/// Moves crates one at a time, so a moved block ends up reversed
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> String {
+        "CrateMover 9000".to_string()
+    }
+
+    fn run_move(&self, stacks: &mut Stacks, cmd: &Move) {
+        for _ in 0..cmd.count {
+            lift(stacks, cmd, 1, 0, 0);
+        }
+    }
+}
+
+/// Moves the whole block at once, keeping its order
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> String {
+        "CrateMover 9001".to_string()
+    }
+
+    fn run_move(&self, stacks: &mut Stacks, cmd: &Move) {
+        lift(stacks, cmd, cmd.count, 0, 0);
+    }
+}
+
+/// Keeps block order but can only lift `capacity` crates per trip
+pub struct CapacityCrane {
+    pub capacity: usize
+}
+
+impl Crane for CapacityCrane {
+    fn name(&self) -> String {
+        format!("capacity {} crane", self.capacity)
+    }
+
+    fn run_move(&self, stacks: &mut Stacks, cmd: &Move) {
+        let mut remaining = cmd.count;
+        while remaining > 0 {
+            let trip = remaining.min(self.capacity.max(1));
+            lift(stacks, cmd, trip, 0, 0);
+            remaining -= trip;
+        }
+    }
+}
+
+/// Moves the block at once but the top `k` crates of it land reversed
+pub struct ReverseTopCrane {
+    pub k: usize
+}
+
+impl Crane for ReverseTopCrane {
+    fn name(&self) -> String {
+        format!("reverse top {} crane", self.k)
+    }
+
+    fn run_move(&self, stacks: &mut Stacks, cmd: &Move) {
+        lift(stacks, cmd, cmd.count, 0, self.k);
+    }
+}
+
+/// Reaches under the top `skip` crates and lifts the block below them, leaving the top in place
+pub struct UnderTopCrane {
+    pub skip: usize
+}
+
+impl Crane for UnderTopCrane {
+    fn name(&self) -> String {
+        format!("under top {} crane", self.skip)
+    }
+
+    fn reach(&self, cmd: &Move) -> usize {
+        cmd.count + self.skip
+    }
+
+    fn run_move(&self, stacks: &mut Stacks, cmd: &Move) {
+        lift(stacks, cmd, cmd.count, self.skip, 0);
+    }
+}
+
+/// Crane from a command line spec: 9000, 9001, capacity:N, reverse:K or under:S
+pub fn crane_from_spec(spec: &str) -> Option<Box<dyn Crane>> {
+    let (kind, param) = match spec.split_once(":") {
+        Some((kind, param)) => (kind, param.parse::<usize>().ok()?),
+        None => (spec, 0)
+    };
+    match kind {
+        "9000" => Some(Box::new(CrateMover9000)),
+        "9001" => Some(Box::new(CrateMover9001)),
+        "capacity" => Some(Box::new(CapacityCrane { capacity: param })),
+        "reverse" => Some(Box::new(ReverseTopCrane { k: param })),
+        "under" => Some(Box::new(UnderTopCrane { skip: param })),
+        _ => None
+    }
+}
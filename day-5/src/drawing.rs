@@ -0,0 +1,184 @@
+use crate::Stacks;
+
+/// Character span [start, end) of each whitespace separated token on a line
+fn token_spans(line: &str) -> Vec<(usize, usize, String)> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut spans = vec![];
+    let mut pos = 0;
+    while pos < chars.len() {
+        if chars[pos].is_whitespace() {
+            pos += 1;
+            continue;
+        }
+        let start = pos;
+        while pos < chars.len() && !chars[pos].is_whitespace() {
+            pos += 1;
+        }
+        spans.push((start, pos, chars[start..pos].iter().collect()));
+    }
+    spans
+}
+
+/// Character span and label of each `[label]` crate on a drawing line
+fn crate_spans(line: &str, line_number: usize) -> Result<Vec<(usize, usize, String)>, String> {
+    let chars = line.chars().collect::<Vec<_>>();
+    let mut spans = vec![];
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '[' => {
+                let close = (pos + 1..chars.len()).find(|&idx| chars[idx] == ']')
+                    .ok_or(format!("line {}: unclosed crate at column {}", line_number, pos + 1))?;
+                if close == pos + 1 {
+                    return Err(format!("line {}: empty crate label at column {}", line_number, pos + 1));
+                }
+                spans.push((pos, close + 1, chars[pos + 1..close].iter().collect()));
+                pos = close + 1;
+            },
+            c if c.is_whitespace() => pos += 1,
+            c => return Err(format!("line {}: unexpected {:?} at column {}", line_number, c, pos + 1))
+        }
+    }
+    Ok(spans)
+}
+
+/// Parse a stack drawing by column position, using the stack number row underneath to place each crate
+pub fn parse_drawing(lines: &[String]) -> Result<Stacks, String> {
+    let (number_row, crate_rows) = lines.split_last().ok_or("empty stack drawing")?;
+    let numbers = token_spans(number_row);
+    if numbers.is_empty() {
+        return Err("missing stack number row".to_string());
+    }
+    for (idx, (_, _, number)) in numbers.iter().enumerate() {
+        if number.parse::<usize>().ok() != Some(idx + 1) {
+            return Err(format!("line {}: expected stack number {} but found {:?}", lines.len(), idx + 1, number));
+        }
+    }
+
+    let mut stacks: Stacks = vec![vec![]; numbers.len()];
+    for (row_idx, row) in crate_rows.iter().enumerate().rev() {
+        let line_number = row_idx + 1;
+        let mut filled = vec![false; numbers.len()];
+        for (start, end, label) in crate_spans(row, line_number)? {
+            // the stack whose number overlaps the crate, otherwise the one whose number is nearest
+            let centre = (start + end) as f64 / 2.0;
+            let stack_idx = numbers.iter().position(|&(n_start, n_end, _)| n_start < end && start < n_end)
+                .unwrap_or_else(|| (0..numbers.len()).min_by(|&a, &b| {
+                    let distance = |idx: usize| ((numbers[idx].0 + numbers[idx].1) as f64 / 2.0 - centre).abs();
+                    distance(a).total_cmp(&distance(b))
+                }).unwrap());
+            if filled[stack_idx] {
+                return Err(format!("line {}: two crates over stack {}", line_number, stack_idx + 1));
+            }
+            if stacks[stack_idx].len() != crate_rows.len() - 1 - row_idx {
+                return Err(format!("line {}: crate {} floats above an empty spot in stack {}", line_number, label, stack_idx + 1));
+            }
+            filled[stack_idx] = true;
+            stacks[stack_idx].push(label);
+        }
+    }
+    Ok(stacks)
+}
+
+/// Draw the stacks the same way the puzzle input does, tallest stack first and numbers underneath
+pub fn render_drawing(stacks: &Stacks) -> Vec<String> {
+    // each column is as wide as its widest crate or its stack number
+    let widths = stacks.iter().enumerate().map(|(idx, stack)| {
+        let widest_crate = stack.iter().map(|label| label.chars().count() + 2).max().unwrap_or(3);
+        widest_crate.max(3).max((idx + 1).to_string().len())
+    }).collect::<Vec<_>>();
+
+    let height = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+    let mut lines = vec![];
+    for level in (0..height).rev() {
+        let cells = stacks.iter().zip(widths.iter()).map(|(stack, &width)| match stack.get(level) {
+            Some(cur_crate) => format!("{:<width$}", format!("[{}]", cur_crate)),
+            None => " ".repeat(width)
+        }).collect::<Vec<_>>();
+        lines.push(cells.join(" "));
+    }
+    lines.push(widths.iter().enumerate().map(|(idx, &width)| {
+        let number = (idx + 1).to_string();
+        let left = (width - number.len()) / 2;
+        format!("{}{}{}", " ".repeat(left), number, " ".repeat(width - left - number.len()))
+    }).collect::<Vec<_>>().join(" "));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|line| line.to_string()).collect()
+    }
+
+    fn stacks(labels: &[&[&str]]) -> Stacks {
+        labels.iter().map(|stack| stack.iter().map(|label| label.to_string()).collect()).collect()
+    }
+
+    /// Drawing parses to the stacks, renders back to exactly the same text, and the rendering parses to the same stacks
+    fn assert_round_trip(drawing: &[&str], expected: &Stacks) {
+        let parsed = parse_drawing(&lines(drawing)).unwrap();
+        assert_eq!(&parsed, expected);
+        let rendered = render_drawing(&parsed);
+        assert_eq!(rendered, lines(drawing));
+        assert_eq!(&parse_drawing(&rendered).unwrap(), expected);
+    }
+
+    #[test]
+    fn sample_drawing() {
+        let drawing = [
+            "    [D]    ",
+            "[N] [C]    ",
+            "[Z] [M] [P]",
+            " 1   2   3 "
+        ];
+        assert_round_trip(&drawing, &stacks(&[&["Z", "N"], &["M", "C", "D"], &["P"]]));
+    }
+
+    #[test]
+    fn ten_or_more_stacks() {
+        let drawing = [
+            "                                    [K]    ",
+            "[A] [B] [C] [D] [E] [F] [G] [H] [I] [J] [L]",
+            " 1   2   3   4   5   6   7   8   9  10  11 "
+        ];
+        let expected = stacks(&[&["A"], &["B"], &["C"], &["D"], &["E"], &["F"], &["G"], &["H"], &["I"], &["J", "K"], &["L"]]);
+        assert_round_trip(&drawing, &expected);
+    }
+
+    #[test]
+    fn multi_character_labels() {
+        let drawing = [
+            "[C]        ",
+            "[AB] [XYZ] ",
+            " 1     2   "
+        ];
+        let parsed = parse_drawing(&lines(&drawing)).unwrap();
+        assert_eq!(parsed, stacks(&[&["AB", "C"], &["XYZ"]]));
+        // columns take the width of their widest crate
+        let canonical = [
+            "[C]       ",
+            "[AB] [XYZ]",
+            " 1     2  "
+        ];
+        assert_round_trip(&canonical, &parsed);
+    }
+
+    #[test]
+    fn ragged_trailing_whitespace() {
+        let drawing = [
+            "    [D]",
+            "[N] [C]  ",
+            "[Z] [M] [P]   ",
+            " 1   2   3"
+        ];
+        let parsed = parse_drawing(&lines(&drawing)).unwrap();
+        let expected = stacks(&[&["Z", "N"], &["M", "C", "D"], &["P"]]);
+        assert_eq!(parsed, expected);
+        let rendered = render_drawing(&parsed);
+        assert!(rendered.iter().map(|line| line.trim_end()).eq(drawing.iter().map(|line| line.trim_end())));
+        assert_round_trip(&["    [D]    ", "[N] [C]    ", "[Z] [M] [P]", " 1   2   3 "], &expected);
+    }
+}
@@ -1,90 +1,276 @@
-use std::{io::{self}, borrow::Borrow};
+use std::{env, fmt, fs, io::{self, BufRead, Write}};
 
-fn stack_builder(lines: &Vec<String>) -> (Vec<Vec<char>>, usize) {
-     // Find first blank line
-    let input_break = lines.iter().position(|line| line.len() == 0).unwrap();
+mod crane;
+mod drawing;
+mod planner;
+mod replay;
+mod validate;
 
-    let num_stacks = lines[input_break-1].split_ascii_whitespace().map(|s| s.parse::<usize>().unwrap()).count();
+use crane::{Crane, CrateMover9000, CrateMover9001, crane_from_spec};
+use drawing::{parse_drawing, render_drawing};
+use planner::{Goal, PlanResult, plan_rearrangement};
+use replay::Replay;
+use validate::validate_procedure;
 
-    let mut stacks: Vec<Vec<char>> = Vec::new();
-    for _ in 0..num_stacks {
-        stacks.push(vec![]);
-    }
+/// Crate label as drawn between the brackets, usually a single letter
+pub type Crate = String;
+pub type Stacks = Vec<Vec<Crate>>;
 
-    for index in (0..input_break-1).rev() {
-        let cur_line = lines[index].replace("    ", "[] ").to_string();
-        let cur_crates = cur_line.split("]").filter(|c| c.len() > 0 ).map(|c| c.chars().last().unwrap()).filter(|&c| c != ' ').collect::<Vec<_>>();
-        for stack_idx in 0..num_stacks {
-            if cur_crates[stack_idx] != '[' {
-                stacks[stack_idx].push(cur_crates[stack_idx]);
-            }
+/// A single `move n from a to b` command, with zero-based stack indexes
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Move {
+    pub count: usize,
+    pub from: usize,
+    pub to: usize
+}
+
+impl Move {
+    /// Parse a `move n from a to b` line, stack numbers start at 1
+    fn parse(move_command: &str) -> Result<Move, String> {
+        let parts = move_command.split_ascii_whitespace().collect::<Vec<_>>();
+        if parts.len() != 6 || parts[0] != "move" || parts[2] != "from" || parts[4] != "to" {
+            return Err(format!("not a move command: {:?}", move_command));
         }
+        let number = |part: &str| part.parse::<usize>().map_err(|_| format!("not a number: {:?}", part));
+        let stack = |part: &str| match number(part)? {
+            0 => Err("stack 0 does not exist".to_string()),
+            n => Ok(n - 1)
+        };
+
+        Ok(Move { count: number(parts[1])?, from: stack(parts[3])?, to: stack(parts[5])? })
+    }
+}
+
+impl fmt::Display for Move {
+    /// Written back in the puzzle's procedure format
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from + 1, self.to + 1)
     }
-    return (stacks, input_break + 1);
 }
 
-fn run_move(stacks: &mut Vec<Vec<char>>, move_command: String, in_order: bool) {
-    let re_moved = move_command.replace("move ","").to_string();
-    let cmd_parts = re_moved.split(" from ").collect::<Vec<_>>();
-    
-    let num_to_move = cmd_parts[0].parse::<usize>().unwrap();
-    
-    let which_stacks = cmd_parts[1].split(" to ").collect::<Vec<_>>();
-    
-    let from_idx = which_stacks[0].parse::<usize>().unwrap() - 1;
-    let to_idx = which_stacks[1].parse::<usize>().unwrap() - 1;
-
-    if in_order {
-        // move in order
-        let mut hold: Vec<char> = Vec::new();
-        for _ in 0..num_to_move {
-            hold.push(*(&stacks[from_idx].pop().unwrap()));
+/// Stacks from the drawing and the index of the blank line ending it, or the line count when there is none
+fn stack_builder(lines: &[String]) -> (Stacks, usize) {
+    // Find first blank line
+    let input_break = lines.iter().position(|line| line.trim().is_empty()).unwrap_or(lines.len());
+
+    let stacks = parse_drawing(&lines[..input_break]).unwrap_or_else(|err| panic!("bad stack drawing: {}", err));
+    (stacks, input_break)
+}
+
+/// Moves of the procedure paired with their line numbers, blank lines skipped
+fn numbered_moves(lines: &[String], first_line_number: usize) -> Vec<(usize, Result<Move, String>)> {
+    lines.iter().enumerate().filter(|(_, line)| !line.trim().is_empty()).map(|(idx, line)| (first_line_number + idx, Move::parse(line))).collect()
+}
+
+fn parse_moves(lines: &[String], first_line_number: usize) -> Vec<Move> {
+    numbered_moves(lines, first_line_number).into_iter().map(|(line_number, cmd)| {
+        cmd.unwrap_or_else(|err| panic!("line {}: {}", line_number, err))
+    }).collect()
+}
+
+/// Replay the whole procedure through a crane, stopping with the index of the first move it cannot carry out
+fn run_procedure(stacks: &mut Stacks, moves: &[Move], crane: &dyn Crane) -> Result<(), usize> {
+    for (idx, cmd) in moves.iter().enumerate() {
+        if !crane.can_run(stacks, cmd) {
+            return Err(idx);
         }
-        for _ in 0..num_to_move {
-            stacks[to_idx].push(hold.pop().unwrap());
+        crane.run_move(stacks, cmd);
+    }
+    Ok(())
+}
+
+fn peek_top_crates(stacks: &Stacks) -> String {
+    let mut tops = String::new();
+
+    for stack in stacks {
+        if let Some(cur_crate) = stack.last() {
+            tops.push_str(cur_crate);
         }
     }
-    else {
-        // one at a time
-        for _ in 0..num_to_move {
-            let cur_crate = &stacks[from_idx].pop().unwrap();
-            stacks[to_idx].push(*cur_crate);
-        }    
+
+    tops
+}
+
+fn print_replay_state(replay: &Replay) {
+    match replay.last_move() {
+        Some(cmd) => println!("After move {}/{}: {}", replay.position(), replay.len(), cmd),
+        None => println!("Start, {} moves", replay.len())
+    }
+    for line in render_drawing(replay.stacks()) {
+        println!("{}", line);
     }
+    if replay.position() < replay.len() && !replay.forward_possible() {
+        println!("Next move cannot be carried out: {}", replay.next_move().unwrap());
+    }
+    println!();
 }
 
-fn peek_top_crates(stacks: Vec<Vec<char>>) -> String {
-    let mut tops = String::new();
+/// Print the drawing after every move of the procedure
+fn trace_replay(mut replay: Replay) {
+    print_replay_state(&replay);
+    while replay.forward() {
+        print_replay_state(&replay);
+    }
+}
+
+/// Step through the procedure with commands read from stdin
+fn interactive_replay(mut replay: Replay) {
+    println!("Commands: n [k] next, b [k] back, j N jump, s show, q quit\n");
+    print_replay_state(&replay);
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().unwrap();
+        let mut command = String::new();
+        if stdin.lock().read_line(&mut command).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = command.split_ascii_whitespace();
+        let verb = parts.next().unwrap_or("s");
+        let amount = parts.next().and_then(|n| n.parse::<usize>().ok());
+        match verb {
+            "n" | "next" => {
+                let target = replay.position() + amount.unwrap_or(1);
+                replay.jump(target);
+            },
+            "b" | "back" => {
+                let target = replay.position().saturating_sub(amount.unwrap_or(1));
+                replay.jump(target);
+            },
+            "j" | "jump" => match amount {
+                Some(target) => {
+                    replay.jump(target);
+                },
+                None => {
+                    println!("jump needs a move number");
+                    continue;
+                }
+            },
+            "s" | "show" => (),
+            "q" | "quit" => return,
+            _ => {
+                println!("unknown command: {}", verb);
+                continue;
+            }
+        }
+        print_replay_state(&replay);
+    }
+}
 
-    for idx in 0..stacks.len() {
-        match stacks[idx].last() {
-            Some(&cur_crate) => tops.push(cur_crate),
-            None => ()
+/// Search for the shortest procedure from the starting drawing to a target drawing or set of top crates
+fn plan(starting_stacks: &Stacks, target_lines: &[String], tops: Option<&String>, crane: &dyn Crane) {
+    let goal = match tops {
+        // comma separated labels, one per stack, '.' where any top will do
+        Some(tops) => Goal::Tops(tops.split(",").map(|label| match label.trim() {
+            "." => None,
+            label => Some(label.to_string())
+        }).collect()),
+        None => {
+            let target_end = target_lines.iter().position(|line| line.trim().is_empty()).unwrap_or(target_lines.len());
+            Goal::Stacks(parse_drawing(&target_lines[..target_end]).unwrap_or_else(|err| panic!("bad target drawing: {}", err)))
         }
+    };
+
+    println!("Plan with {}\r\n{}", crane.name(), "-".repeat(10));
+    match plan_rearrangement(starting_stacks, &goal, crane) {
+        PlanResult::Found(moves) => {
+            let mut stacks = starting_stacks.clone();
+            run_procedure(&mut stacks, &moves, crane).unwrap();
+            for line in render_drawing(&stacks) {
+                println!("{}", line);
+            }
+            println!("\nShortest procedure: {} moves\n", moves.len());
+            for cmd in moves {
+                println!("{}", cmd);
+            }
+        },
+        PlanResult::Impossible(reason) => println!("No procedure exists: {}", reason),
+        PlanResult::GaveUp => println!("Search gave up before finding a procedure")
     }
-    
-    return tops;
 }
 
 fn main() {
-    let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lines().flatten().collect();
+    let args: Vec<String> = env::args().skip(1).collect();
+    let puzzle_file = match args.first().map(|s| s.as_str()) {
+        Some("replay") => args.get(2),
+        _ => None
+    };
+    let lines: Vec<String> = match puzzle_file {
+        Some(path) => fs::read_to_string(path).expect("cannot read puzzle file").lines().map(|line| line.to_string()).collect(),
+        None => io::stdin().lines().map_while(Result::ok).collect()
+    };
+    let (starting_stacks, input_break) = stack_builder(&lines);
+    let cmd_start_at = (input_break + 1).min(lines.len());
+    if args.first().map(|s| s.as_str()) == Some("validate") {
+        let spec = args.get(1).map(|s| s.as_str()).unwrap_or("9000");
+        let crane = crane_from_spec(spec).unwrap_or_else(|| panic!("unknown crane: {}", spec));
+        let clamp = args.get(2).map(|s| s.as_str()) == Some("clamp");
+        validate_procedure(starting_stacks, &numbered_moves(&lines[cmd_start_at..], cmd_start_at + 1), crane.as_ref(), clamp);
+        return;
+    }
 
-    println!("Part 1\r\n{}", "-".repeat(10));
-    let (mut stacks, cmd_start_at)  = stack_builder(&lines);
+    if args.first().map(|s| s.as_str()) == Some("plan") {
+        // The target drawing takes the place of the move list unless top crates are given
+        let spec = args.get(1).map(|s| s.as_str()).unwrap_or("9000");
+        let crane = crane_from_spec(spec).unwrap_or_else(|| panic!("unknown crane: {}", spec));
+        plan(&starting_stacks, &lines[cmd_start_at..], args.get(2), crane.as_ref());
+        return;
+    }
 
-    let end = lines.len();
-    for cmd_idx in cmd_start_at..end {
-        run_move(&mut stacks, lines[cmd_idx].to_string(), false)
+    if args.first().map(|s| s.as_str()) == Some("drawing") {
+        // Round trip the stack drawing through the parser and serializer
+        let original = &lines[..input_break];
+        let rendered = render_drawing(&starting_stacks);
+        for line in rendered.iter() {
+            println!("{}", line);
+        }
+        let exact = rendered.as_slice() == original;
+        let trimmed = rendered.iter().map(|line| line.trim_end()).eq(original.iter().map(|line| line.trim_end()));
+        println!("\nStacks: {}, crates: {}", starting_stacks.len(), starting_stacks.iter().map(|stack| stack.len()).sum::<usize>());
+        println!("Identical to input: {}", exact);
+        println!("Identical ignoring trailing whitespace: {}", trimmed);
+        return;
     }
-    println!("Top crates, one at a time: {}\n", peek_top_crates(stacks));
 
-    println!("Part 2\r\n{}", "-".repeat(10));
-    let (mut stacks, cmd_start_at)  = stack_builder(&lines);
+    let moves = parse_moves(&lines[cmd_start_at..], cmd_start_at + 1);
 
-    let end = lines.len();
-    for cmd_idx in cmd_start_at..end {
-        run_move(&mut stacks, lines[cmd_idx].to_string(), true)
+    if args.first().map(|s| s.as_str()) == Some("replay") {
+        // With a puzzle file stdin is free for stepping commands, otherwise trace every move
+        let spec = args.get(1).map(|s| s.as_str()).unwrap_or("9000");
+        let crane = crane_from_spec(spec).unwrap_or_else(|| panic!("unknown crane: {}", spec));
+        let replay = Replay::new(starting_stacks, &moves, crane.as_ref());
+        match puzzle_file {
+            Some(_) => interactive_replay(replay),
+            None => trace_replay(replay)
+        }
+        return;
     }
-    println!("Top crates in order: {}", peek_top_crates(stacks));
+
+    if args.first().map(|s| s.as_str()) == Some("cranes") {
+        // Replay the same move list through several machines
+        let mut specs = args[1..].to_vec();
+        if specs.is_empty() {
+            specs = ["9000", "9001", "capacity:3", "reverse:2", "under:1"].iter().map(|s| s.to_string()).collect();
+        }
+        println!("Cranes\r\n{}", "-".repeat(10));
+        for spec in specs {
+            let crane = crane_from_spec(&spec).unwrap_or_else(|| panic!("unknown crane: {}", spec));
+            let mut stacks = starting_stacks.clone();
+            match run_procedure(&mut stacks, &moves, crane.as_ref()) {
+                Ok(()) => println!("{:<24} {}", crane.name(), peek_top_crates(&stacks)),
+                Err(idx) => println!("{:<24} stuck at move {}: {}", crane.name(), idx + 1, moves[idx])
+            }
+        }
+        return;
+    }
+
+    println!("Part 1\r\n{}", "-".repeat(10));
+    let mut stacks = starting_stacks.clone();
+    run_procedure(&mut stacks, &moves, &CrateMover9000).unwrap();
+    println!("Top crates, one at a time: {}\n", peek_top_crates(&stacks));
+
+    println!("Part 2\r\n{}", "-".repeat(10));
+    let mut stacks = starting_stacks.clone();
+    run_procedure(&mut stacks, &moves, &CrateMover9001).unwrap();
+    println!("Top crates in order: {}", peek_top_crates(&stacks));
 }
@@ -0,0 +1,161 @@
+use std::{collections::HashMap, mem::size_of, rc::Rc};
+
+use crate::{Crate, Move, Stacks, crane::Crane};
+
+/// Memory the remembered stack arrangements may take before the planner gives up
+const PLANNER_MEMORY_BUDGET: usize = 512 * 1024 * 1024;
+
+/// Stacks packed as one crate code per crate, each stack closed by `SEPARATOR`
+type StateCode = Rc<[u16]>;
+
+/// Packs stack arrangements compactly by numbering the crate labels
+struct StateCodec {
+    labels: Vec<Crate>,
+    stack_count: usize
+}
+
+impl StateCodec {
+    const SEPARATOR: u16 = u16::MAX;
+
+    fn new(start: &Stacks) -> StateCodec {
+        let mut labels = start.iter().flatten().cloned().collect::<Vec<_>>();
+        labels.sort();
+        labels.dedup();
+        assert!(labels.len() < StateCodec::SEPARATOR as usize, "too many different crate labels to plan with");
+        StateCodec { labels, stack_count: start.len() }
+    }
+
+    fn encode(&self, stacks: &Stacks) -> StateCode {
+        stacks.iter().flat_map(|stack| {
+            stack.iter().map(|label| self.labels.binary_search(label).unwrap() as u16).chain([StateCodec::SEPARATOR])
+        }).collect()
+    }
+
+    fn decode(&self, code: &[u16]) -> Stacks {
+        code.split(|&c| c == StateCodec::SEPARATOR).take(self.stack_count)
+            .map(|stack| stack.iter().map(|&c| self.labels[c as usize].clone()).collect())
+            .collect()
+    }
+
+    /// Rough bytes held per remembered state: the shared code, its two handles, the map slot and the parent link
+    fn bytes_per_state(&self, stacks: &Stacks) -> usize {
+        let code_len = stacks.iter().map(|stack| stack.len() + 1).sum::<usize>();
+        code_len * size_of::<u16>() + 2 * size_of::<usize>() + 2 * size_of::<StateCode>() + size_of::<(StateCode, usize)>() + size_of::<Option<(usize, Move)>>()
+    }
+}
+
+/// What the rearrangement has to achieve
+pub enum Goal {
+    /// Every stack exactly as drawn
+    Stacks(Stacks),
+    /// Label on top of each stack, None where any crate (or none) will do
+    Tops(Vec<Option<String>>)
+}
+
+impl Goal {
+    fn is_met(&self, stacks: &Stacks) -> bool {
+        match self {
+            Goal::Stacks(target) => stacks == target,
+            Goal::Tops(tops) => tops.iter().zip(stacks.iter()).all(|(top, stack)| match top {
+                Some(label) => stack.last() == Some(label),
+                None => true
+            })
+        }
+    }
+
+    /// Reason the goal can never be reached from these stacks, if there is an obvious one
+    fn impossible(&self, stacks: &Stacks) -> Option<String> {
+        let mut have = stacks.iter().flatten().collect::<Vec<_>>();
+        have.sort();
+        match self {
+            Goal::Stacks(target) => {
+                let mut want = target.iter().flatten().collect::<Vec<_>>();
+                want.sort();
+                if target.len() != stacks.len() {
+                    Some(format!("target has {} stacks but the start has {}", target.len(), stacks.len()))
+                }
+                else if want != have {
+                    Some("target does not hold the same crates as the start".to_string())
+                }
+                else {
+                    None
+                }
+            },
+            Goal::Tops(tops) => {
+                if tops.len() != stacks.len() {
+                    return Some(format!("{} tops given for {} stacks", tops.len(), stacks.len()));
+                }
+                let mut wanted = tops.iter().flatten().collect::<Vec<_>>();
+                wanted.sort();
+                // each wanted top needs its own crate
+                for label in wanted.iter() {
+                    let need = wanted.iter().filter(|&w| w == label).count();
+                    if have.iter().filter(|&&h| h == *label).count() < need {
+                        return Some(format!("not enough {} crates", label));
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+pub enum PlanResult {
+    Found(Vec<Move>),
+    Impossible(String),
+    GaveUp
+}
+
+/// Breadth-first search for the shortest procedure that reaches the goal with the given crane
+pub fn plan_rearrangement(start: &Stacks, goal: &Goal, crane: &dyn Crane) -> PlanResult {
+    if let Some(reason) = goal.impossible(start) {
+        return PlanResult::Impossible(reason);
+    }
+
+    // states in the order found, which is also breadth-first order, each with the state and move it was reached from
+    let codec = StateCodec::new(start);
+    let state_limit = PLANNER_MEMORY_BUDGET / codec.bytes_per_state(start);
+    let mut states: Vec<(StateCode, Option<(usize, Move)>)> = vec![];
+    let mut seen: HashMap<StateCode, usize> = HashMap::new();
+    let start_code = codec.encode(start);
+    seen.insert(start_code.clone(), 0);
+    states.push((start_code, None));
+
+    let mut idx = 0;
+    while idx < states.len() {
+        let stacks = codec.decode(&states[idx].0);
+        if goal.is_met(&stacks) {
+            let mut moves = vec![];
+            let mut cur = idx;
+            while let Some((prev, cmd)) = states[cur].1 {
+                moves.push(cmd);
+                cur = prev;
+            }
+            moves.reverse();
+            return PlanResult::Found(moves);
+        }
+        if states.len() > state_limit {
+            return PlanResult::GaveUp;
+        }
+
+        for from in 0..stacks.len() {
+            for to in (0..stacks.len()).filter(|&to| to != from) {
+                for count in 1..=stacks[from].len() {
+                    let cmd = Move { count, from, to };
+                    if !crane.can_run(&stacks, &cmd) {
+                        break;
+                    }
+                    let mut next = stacks.clone();
+                    crane.run_move(&mut next, &cmd);
+                    let code = codec.encode(&next);
+                    if !seen.contains_key(&code) {
+                        seen.insert(code.clone(), states.len());
+                        states.push((code, Some((idx, cmd))));
+                    }
+                }
+            }
+        }
+        idx += 1;
+    }
+    PlanResult::Impossible("no sequence of moves reaches the goal".to_string())
+}
@@ -0,0 +1,77 @@
+use crate::{Crate, Move, Stacks, crane::Crane};
+
+/// Steps a procedure through a crane, keeping enough history to undo every move
+pub struct Replay<'a> {
+    stacks: Stacks,
+    moves: &'a [Move],
+    crane: &'a dyn Crane,
+    // contents of the source and destination stacks before each applied move
+    history: Vec<[(usize, Vec<Crate>); 2]>
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(stacks: Stacks, moves: &'a [Move], crane: &'a dyn Crane) -> Self {
+        Replay { stacks, moves, crane, history: vec![] }
+    }
+
+    pub fn stacks(&self) -> &Stacks {
+        &self.stacks
+    }
+
+    /// Number of moves applied so far
+    pub fn position(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Move that will be applied by the next forward step
+    pub fn next_move(&self) -> Option<&Move> {
+        self.moves.get(self.position())
+    }
+
+    /// Most recently applied move
+    pub fn last_move(&self) -> Option<&Move> {
+        self.position().checked_sub(1).map(|idx| &self.moves[idx])
+    }
+
+    pub fn forward_possible(&self) -> bool {
+        match self.next_move() {
+            Some(cmd) => self.crane.can_run(&self.stacks, cmd),
+            None => false
+        }
+    }
+
+    /// Apply the next move, false at the end of the procedure or if the crane cannot carry it out
+    pub fn forward(&mut self) -> bool {
+        if !self.forward_possible() {
+            return false;
+        }
+        let cmd = self.moves[self.position()];
+        self.history.push([(cmd.from, self.stacks[cmd.from].clone()), (cmd.to, self.stacks[cmd.to].clone())]);
+        self.crane.run_move(&mut self.stacks, &cmd);
+        true
+    }
+
+    /// Undo the last applied move, false when already at the start
+    pub fn back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(saved) => {
+                for (idx, stack) in saved {
+                    self.stacks[idx] = stack;
+                }
+                true
+            },
+            None => false
+        }
+    }
+
+    /// Step forward or back until `position` moves have been applied, returning the position reached
+    pub fn jump(&mut self, position: usize) -> usize {
+        while self.position() > position && self.back() {}
+        while self.position() < position && self.forward() {}
+        self.position()
+    }
+}
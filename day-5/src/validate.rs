@@ -0,0 +1,70 @@
+use crate::{Move, Stacks, crane::Crane, peek_top_crates};
+
+/// Why a move cannot be carried out on the stacks as they stand
+fn move_problem(stacks: &Stacks, cmd: &Move, crane: &dyn Crane) -> Option<String> {
+    let stack_exists = |idx: usize| idx < stacks.len();
+    if !stack_exists(cmd.from) {
+        Some(format!("stack {} does not exist", cmd.from + 1))
+    }
+    else if !stack_exists(cmd.to) {
+        Some(format!("stack {} does not exist", cmd.to + 1))
+    }
+    else if cmd.from == cmd.to {
+        Some("source and destination are the same stack".to_string())
+    }
+    else if !crane.can_run(stacks, cmd) {
+        Some(format!("needs {} crates on stack {} but it holds {}", crane.reach(cmd), cmd.from + 1, stacks[cmd.from].len()))
+    }
+    else {
+        None
+    }
+}
+
+fn stack_sizes(stacks: &Stacks) -> String {
+    stacks.iter().map(|stack| stack.len().to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// Check the whole procedure against the starting stacks, reporting every move that cannot be carried out.
+/// Invalid moves are skipped, or in clamp mode cut down to the crates that exist.
+pub fn validate_procedure(mut stacks: Stacks, moves: &[(usize, Result<Move, String>)], crane: &dyn Crane, clamp: bool) {
+    println!("Validating {} moves with {}{}\r\n{}", moves.len(), crane.name(), if clamp { ", clamping" } else { "" }, "-".repeat(10));
+
+    let mut invalid = 0;
+    let mut clamped = 0;
+    for (line_number, parsed) in moves {
+        let cmd = match parsed {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                invalid += 1;
+                println!("line {:>4}: {}", line_number, err);
+                continue;
+            }
+        };
+        let problem = match move_problem(&stacks, cmd, crane) {
+            Some(problem) => problem,
+            None => {
+                crane.run_move(&mut stacks, cmd);
+                continue;
+            }
+        };
+
+        invalid += 1;
+        println!("line {:>4}: {}: {} (stack sizes: {})", line_number, cmd, problem, stack_sizes(&stacks));
+        if clamp {
+            if let Some(reduced) = crane.clamp(&stacks, cmd) {
+                if reduced.count > 0 {
+                    clamped += 1;
+                    println!("           clamped to {}", reduced);
+                    crane.run_move(&mut stacks, &reduced);
+                }
+            }
+        }
+    }
+
+    println!("\nInvalid moves: {}", invalid);
+    if clamp {
+        println!("Clamped moves: {}", clamped);
+    }
+    println!("Final stack sizes: {}", stack_sizes(&stacks));
+    println!("Top crates: {}", peek_top_crates(&stacks));
+}
@@ -0,0 +1,102 @@
+use crate::marker::{MarkerDefinition, WindowDetector};
+
+/// Number of markers and messages listed per definition before the rest are summarized
+const LIST_LIMIT: usize = 10;
+
+/// Every non-overlapping marker of each definition, as one-based end positions, in a single pass over the datastream
+pub fn find_all_markers(datastream: &[u8], definitions: &[MarkerDefinition]) -> Vec<Vec<usize>> {
+    let mut detectors = definitions.iter().map(|definition| WindowDetector::new(definition.clone())).collect::<Vec<_>>();
+    let mut markers = vec![vec![]; definitions.len()];
+    for &byte in datastream {
+        for (detector, found) in detectors.iter_mut().zip(markers.iter_mut()) {
+            if let Some(position) = detector.push(byte) {
+                found.push(position);
+                detector.reset();
+            }
+        }
+    }
+    markers
+}
+
+/// Byte ranges [start, end) of the messages following each marker, up to the next marker's window
+pub fn segment_messages(datastream: &[u8], markers: &[usize], window_size: usize) -> Vec<(usize, usize)> {
+    markers.iter().enumerate().map(|(idx, &marker_end)| {
+        let end = match markers.get(idx + 1) {
+            Some(&next_marker_end) => next_marker_end - window_size,
+            None => datastream.len()
+        };
+        (marker_end, end)
+    }).collect()
+}
+
+fn preview(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    if text.chars().count() > 40 {
+        format!("{}...", text.chars().take(40).collect::<String>())
+    }
+    else {
+        text.to_string()
+    }
+}
+
+/// Report every marker and message for each registered definition, one datastream per line
+pub fn decode(lines: &[String], definitions: &[MarkerDefinition]) {
+    for (line_idx, line) in lines.iter().enumerate().filter(|(_, line)| !line.is_empty()) {
+        let datastream = line.as_bytes();
+        println!("Datastream {} ({} bytes)\r\n{}", line_idx + 1, datastream.len(), "-".repeat(10));
+
+        for (definition, markers) in definitions.iter().zip(find_all_markers(datastream, definitions)) {
+            println!("{} (window {}, {:?}): {} markers", definition.name, definition.window_size, definition.rule, markers.len());
+            let messages = segment_messages(datastream, &markers, definition.window_size);
+            for (idx, &(start, end)) in messages.iter().enumerate().take(LIST_LIMIT) {
+                let message = if start == end {
+                    "empty".to_string()
+                }
+                else {
+                    format!("bytes {}-{} ({} bytes) {:?}", start + 1, end, end - start, preview(&datastream[start..end]))
+                };
+                println!("  marker at {:>6}, message {:>3}: {}", markers[idx], idx + 1, message);
+            }
+            if messages.len() > LIST_LIMIT {
+                println!("  ... {} more", messages.len() - LIST_LIMIT);
+            }
+        }
+        println!();
+    }
+}
+
+/// Earliest marker for each definition as the number of tolerated duplicates grows from 0 to `max_duplicates`
+pub fn tolerance_report(lines: &[String], definitions: &[MarkerDefinition], max_duplicates: usize, wildcard: Option<u8>) {
+    for (line_idx, line) in lines.iter().enumerate().filter(|(_, line)| !line.is_empty()) {
+        let datastream = line.as_bytes();
+        let noise = wildcard.map_or(0, |w| datastream.iter().filter(|&&b| b == w).count());
+        println!("Datastream {} ({} bytes, {} noise symbols)\r\n{}", line_idx + 1, datastream.len(), noise, "-".repeat(10));
+
+        for definition in definitions {
+            // every tolerance level runs side by side in one pass
+            let tolerant = (0..=max_duplicates).map(|k| definition.with_tolerance(k, wildcard)).collect::<Vec<_>>();
+            let mut detectors = tolerant.iter().map(|d| WindowDetector::new(d.clone())).collect::<Vec<_>>();
+            let mut earliest: Vec<Option<usize>> = vec![None; tolerant.len()];
+            for &byte in datastream {
+                for (detector, found) in detectors.iter_mut().zip(earliest.iter_mut()) {
+                    let position = detector.push(byte);
+                    if found.is_none() {
+                        *found = position;
+                    }
+                }
+                if earliest.iter().all(|found| found.is_some()) {
+                    break;
+                }
+            }
+
+            println!("{} (window {}, {:?}):", definition.name, definition.window_size, definition.rule);
+            for (k, found) in earliest.iter().enumerate() {
+                match found {
+                    Some(position) => println!("  up to {} duplicates: {}", k, position),
+                    None => println!("  up to {} duplicates: no marker", k)
+                }
+            }
+        }
+        println!();
+    }
+}
@@ -1,41 +1,62 @@
-use std::io::{self};
+use std::{env, fs::File, io::{self}};
 
-fn get_start_of_packet(datastream: String, window_size: usize) -> Option<usize> {
+mod decoder;
+mod marker;
 
-    let chars = datastream.chars().collect::<Vec<_>>();
+use decoder::{decode, tolerance_report};
+use marker::{MarkerDefinition, find_marker};
 
-    let windows = chars.windows(window_size).enumerate();
+fn get_start_of_packet(datastream: &str, window_size: usize) -> Option<usize> {
+    find_marker(datastream.as_bytes(), window_size).unwrap()
+}
 
-    let mut start_of_packet: usize = 0;
-    'window: for (stream_position, window) in windows {
-        let mut has_dup = false;
-        'unique_check: for idx in 0..window_size - 1 {
-            for check_idx in idx + 1..window_size {
-                if window[idx] == window[check_idx] {
-                    has_dup = true;
-                    break 'unique_check;
-                }
-            }
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("scan") {
+        // Stream straight from a file or stdin so inputs of any size never sit in memory
+        let window_size = args.get(1).map(|w| w.parse::<usize>().expect("window size must be a number")).unwrap_or(14);
+        let start_of_packet = match args.get(2) {
+            Some(path) => find_marker(File::open(path).expect("cannot open datastream"), window_size),
+            None => find_marker(io::stdin().lock(), window_size)
+        }.expect("cannot read datastream");
+        match start_of_packet {
+            Some(position) => println!("Number of characters before start of packet: {}", position),
+            None => println!("No {} character marker found", window_size)
         }
-        if !has_dup {
-            // We have a unique character set window, so add window to position for start of message
-            return Some(stream_position + window_size);
+        return;
+    }
+
+    if args.first().map(|s| s.as_str()) == Some("decode") {
+        // Find every marker of each definition on every line
+        let mut specs = args[1..].to_vec();
+        if specs.is_empty() {
+            specs = vec!["packet:4".to_string(), "message:14".to_string()];
         }
+        let definitions = specs.iter().map(|spec| MarkerDefinition::from_spec(spec).unwrap_or_else(|| panic!("bad marker definition: {}", spec))).collect::<Vec<_>>();
+        let lines: Vec<String> = io::stdin().lines().map_while(Result::ok).collect();
+        decode(&lines, &definitions);
+        return;
+    }
+
+    if args.first().map(|s| s.as_str()) == Some("tolerance") {
+        // Earliest packet and message markers when some corruption is allowed
+        let max_duplicates = args.get(1).map(|k| k.parse::<usize>().expect("duplicates must be a number")).unwrap_or(2);
+        let wildcard = args.get(2).map(|w| *w.as_bytes().first().expect("wildcard must be a single character"));
+        let definitions = ["packet:4", "message:14"].iter().map(|spec| MarkerDefinition::from_spec(spec).unwrap()).collect::<Vec<_>>();
+        let lines: Vec<String> = io::stdin().lines().map_while(Result::ok).collect();
+        tolerance_report(&lines, &definitions, max_duplicates, wildcard);
+        return;
     }
-    return None;
-}
 
-fn main() {
     let stdin = io::stdin();
-    //let signal = stdin.lines().nth(0).unwrap().unwrap().chars().collect::<Vec<_>>();
-    let datastream = stdin.lines().nth(0).unwrap().unwrap();
+    let datastream = stdin.lines().next().unwrap().unwrap();
 
     println!("Part 1\r\n{}", "-".repeat(10));
-    let start_of_packet = get_start_of_packet(datastream.to_string(), 4);
+    let start_of_packet = get_start_of_packet(&datastream, 4);
     println!("Number of characters before start of packet: {:?}\n", start_of_packet.unwrap());
 
 
     println!("Part 2\r\n{}", "-".repeat(10));
-    let start_of_packet = get_start_of_packet(datastream.to_string(), 14);
+    let start_of_packet = get_start_of_packet(&datastream, 14);
     println!("Number of characters before start of packet: {:?}\n", start_of_packet.unwrap());
 }
@@ -0,0 +1,161 @@
+use std::{collections::VecDeque, io::{self, Read}};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Sliding window detector that finds the first run of `window_size` distinct bytes in O(n)
+pub struct MarkerDetector {
+    window_size: usize,
+    // one-based stream position where each byte value was last seen, zero for never
+    last_seen: [usize; 256],
+    window_start: usize,
+    position: usize
+}
+
+impl MarkerDetector {
+    pub fn new(window_size: usize) -> Self {
+        MarkerDetector { window_size: window_size.max(1), last_seen: [0; 256], window_start: 1, position: 0 }
+    }
+
+    /// Feed the next byte, returning the number of bytes read so far when it completes a marker
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+        let seen = self.last_seen[byte as usize];
+        if seen >= self.window_start {
+            // repeated inside the window, so the window now starts just after the earlier copy
+            self.window_start = seen + 1;
+        }
+        self.last_seen[byte as usize] = self.position;
+
+        if self.position + 1 - self.window_start >= self.window_size {
+            Some(self.position)
+        }
+        else {
+            None
+        }
+    }
+}
+
+/// Scan a reader chunk by chunk for the first marker, stopping at the end of the line
+pub fn find_marker<R: Read>(mut reader: R, window_size: usize) -> io::Result<Option<usize>> {
+    let mut detector = MarkerDetector::new(window_size);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(None),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err)
+        };
+        for &byte in &buffer[..read] {
+            if byte == b'\n' || byte == b'\r' {
+                return Ok(None);
+            }
+            if let Some(position) = detector.push(byte) {
+                return Ok(Some(position));
+            }
+        }
+    }
+}
+
+/// How the bytes of a window have to differ for it to count as a marker
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UniquenessRule {
+    /// Every byte in the window is different
+    AllDistinct,
+    /// Every letter is different once case is ignored
+    IgnoreCase
+}
+
+impl UniquenessRule {
+    fn normalize(&self, byte: u8) -> u8 {
+        match self {
+            UniquenessRule::AllDistinct => byte,
+            UniquenessRule::IgnoreCase => byte.to_ascii_lowercase()
+        }
+    }
+}
+
+/// A named kind of marker, such as start-of-packet or start-of-message
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MarkerDefinition {
+    pub name: String,
+    pub window_size: usize,
+    pub rule: UniquenessRule,
+    /// Repeated bytes a window may hold and still count, for noisy captures
+    pub max_duplicates: usize,
+    /// Noise symbol that stands in for any byte and never counts as a repeat
+    pub wildcard: Option<u8>
+}
+
+impl MarkerDefinition {
+    /// Definition from a `name:window[:rule]` spec, rule being `distinct` or `nocase`
+    pub fn from_spec(spec: &str) -> Option<MarkerDefinition> {
+        let mut parts = spec.split(":");
+        let name = parts.next()?.to_string();
+        let window_size = parts.next()?.parse::<usize>().ok()?;
+        let rule = match parts.next() {
+            None | Some("distinct") => UniquenessRule::AllDistinct,
+            Some("nocase") => UniquenessRule::IgnoreCase,
+            Some(_) => return None
+        };
+        Some(MarkerDefinition { name, window_size: window_size.max(1), rule, max_duplicates: 0, wildcard: None })
+    }
+
+    /// Same marker, tolerating up to `max_duplicates` repeats and an optional noise symbol
+    pub fn with_tolerance(&self, max_duplicates: usize, wildcard: Option<u8>) -> MarkerDefinition {
+        MarkerDefinition { max_duplicates, wildcard, ..self.clone() }
+    }
+}
+
+/// Fixed size sliding window keeping byte counts, so rules and tolerances can be checked per byte
+pub struct WindowDetector {
+    definition: MarkerDefinition,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    duplicates: usize,
+    position: usize
+}
+
+impl WindowDetector {
+    pub fn new(definition: MarkerDefinition) -> Self {
+        WindowDetector { window: VecDeque::with_capacity(definition.window_size), definition, counts: [0; 256], duplicates: 0, position: 0 }
+    }
+
+    /// Forget the current window so the next marker cannot overlap the last one
+    pub fn reset(&mut self) {
+        self.window.clear();
+        self.counts = [0; 256];
+        self.duplicates = 0;
+    }
+
+    /// Feed the next byte, returning the number of bytes read so far when the window is a marker
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+        let rule = self.definition.rule;
+        let wildcard = self.definition.wildcard.map(|w| rule.normalize(w));
+        let byte = rule.normalize(byte);
+        if self.window.len() == self.definition.window_size {
+            let oldest = self.window.pop_front().unwrap();
+            if Some(oldest) != wildcard {
+                self.counts[oldest as usize] -= 1;
+                if self.counts[oldest as usize] > 0 {
+                    self.duplicates -= 1;
+                }
+            }
+        }
+        if Some(byte) != wildcard {
+            if self.counts[byte as usize] > 0 {
+                self.duplicates += 1;
+            }
+            self.counts[byte as usize] += 1;
+        }
+        self.window.push_back(byte);
+
+        if self.window.len() == self.definition.window_size && self.duplicates <= self.definition.max_duplicates {
+            Some(self.position)
+        }
+        else {
+            None
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::fstree::{FSTree, NodeId};
+
+/// Something in the transcript that makes the reconstructed sizes less trustworthy
+pub enum Issue {
+    /// A directory listed again with the same contents, the copy was ignored
+    RepeatedListing { line: usize, dir: NodeId },
+    /// A file listed again with a different size, the later size was kept
+    ConflictingSize { line: usize, dir: NodeId, name: String, earlier: usize, later: usize },
+    /// An entry from an earlier listing missing from a later one of the same directory
+    MissingFromRelisting { line: usize, dir: NodeId, name: String },
+    /// An entry in a later listing of a directory that an earlier one did not show
+    AddedInRelisting { line: usize, dir: NodeId, name: String },
+    /// `cd` into a directory no listing mentioned
+    UnknownDir { line: usize, dir: NodeId, parent_listed: bool },
+    /// `cd ..` while already at the root
+    AboveRoot { line: usize },
+    UnknownCommand { line: usize, text: String },
+    /// Output that does not follow an `ls`
+    StrayOutput { line: usize, text: String },
+    MalformedEntry { line: usize, text: String },
+    /// A directory known from a listing or `cd` whose own contents were never listed
+    NeverListed { dir: NodeId }
+}
+
+impl Issue {
+    /// Transcript line the issue was found on, none for issues about the final tree
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            Issue::RepeatedListing { line, .. } | Issue::ConflictingSize { line, .. } | Issue::MissingFromRelisting { line, .. } | Issue::AddedInRelisting { line, .. } |
+            Issue::UnknownDir { line, .. } | Issue::AboveRoot { line } | Issue::UnknownCommand { line, .. } |
+            Issue::StrayOutput { line, .. } | Issue::MalformedEntry { line, .. } => Some(line),
+            Issue::NeverListed { .. } => None
+        }
+    }
+
+    pub fn describe(&self, tree: &FSTree) -> String {
+        match self {
+            Issue::RepeatedListing { dir, .. } => format!("{} listed again with the same contents, ignored", tree.path(*dir)),
+            Issue::ConflictingSize { dir, name, earlier, later, .. } => format!("{} listed as {} bytes after {} earlier, keeping {}", tree.file_path(*dir, name), later, earlier, later),
+            Issue::MissingFromRelisting { dir, name, .. } => format!("{} missing from a later listing of {}", name, tree.path(*dir)),
+            Issue::AddedInRelisting { dir, name, .. } => format!("{} appears in a later listing of {} only", name, tree.path(*dir)),
+            Issue::UnknownDir { dir, parent_listed: true, .. } => format!("cd into {} which the listing of its parent does not show", tree.path(*dir)),
+            Issue::UnknownDir { dir, parent_listed: false, .. } => format!("cd into {} before its parent was listed", tree.path(*dir)),
+            Issue::AboveRoot { .. } => "cd .. at the root".to_string(),
+            Issue::UnknownCommand { text, .. } => format!("unknown command: {}", text),
+            Issue::StrayOutput { text, .. } => format!("output outside a listing: {}", text),
+            Issue::MalformedEntry { text, .. } => format!("malformed listing entry: {}", text),
+            Issue::NeverListed { dir } => format!("{} was never listed", tree.path(*dir))
+        }
+    }
+}
+
+/// How far the computed size of a directory can be trusted, worst first
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Confidence {
+    /// Something below was never listed, the real size can only be larger
+    LowerBound,
+    /// Listings below disagreed, the size depends on which one was right
+    Conflicted,
+    Exact
+}
+
+impl Confidence {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Confidence::LowerBound => "lower bound",
+            Confidence::Conflicted => "conflicted",
+            Confidence::Exact => "exact"
+        }
+    }
+}
+
+/// Confidence of every directory's size, taking the worst of its own listing and everything below it
+pub fn size_confidence(tree: &FSTree, listed: &HashMap<NodeId, BTreeSet<String>>, issues: &[Issue]) -> Vec<(NodeId, Confidence)> {
+    let conflicted = issues.iter().filter_map(|issue| match *issue {
+        Issue::ConflictingSize { dir, .. } | Issue::MissingFromRelisting { dir, .. } | Issue::AddedInRelisting { dir, .. } => Some(dir),
+        _ => None
+    }).collect::<HashSet<_>>();
+
+    let order = tree.post_order(FSTree::ROOT);
+    let mut confidence = vec![Confidence::Exact; tree.len()];
+    for &id in order.iter() {
+        let own = if !listed.contains_key(&id) {
+            Confidence::LowerBound
+        } else if conflicted.contains(&id) {
+            Confidence::Conflicted
+        } else {
+            Confidence::Exact
+        };
+        confidence[id] = tree.children(id).iter().map(|&child| confidence[child]).fold(own, Confidence::min);
+    }
+    tree.depth_first(FSTree::ROOT).map(|id| (id, confidence[id])).collect()
+}
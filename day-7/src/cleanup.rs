@@ -0,0 +1,246 @@
+use crate::fstree::{FSTree, NodeId};
+
+/// Memory the rows of reachable sums may take, past it sizes are counted in coarser blocks
+const CLEANUP_MEMORY_BUDGET: usize = 64 * 1024 * 1024;
+/// Bits of rows the planner may combine, past it sizes are counted in coarser blocks to keep it quick
+const CLEANUP_WORK_BUDGET: usize = 1 << 37;
+
+/// Fixed size set of sums below its capacity, one bit per byte count
+#[derive(Clone)]
+struct SumSet {
+    words: Vec<u64>,
+    capacity: usize
+}
+
+impl SumSet {
+    fn new(capacity: usize) -> Self {
+        SumSet { words: vec![0; capacity.div_ceil(64)], capacity }
+    }
+
+    fn insert(&mut self, sum: usize) {
+        if sum < self.capacity {
+            self.words[sum / 64] |= 1 << (sum % 64);
+        }
+    }
+
+    fn contains(&self, sum: usize) -> bool {
+        sum < self.capacity && self.words[sum / 64] & (1 << (sum % 64)) != 0
+    }
+
+    /// Add every sum of `other` increased by `shift`, dropping those past capacity
+    fn union_shifted(&mut self, other: &SumSet, shift: usize) {
+        let (word_shift, bit_shift) = (shift / 64, shift % 64);
+        for idx in (word_shift..self.words.len()).rev() {
+            let src = idx - word_shift;
+            let mut word = other.words[src] << bit_shift;
+            if bit_shift > 0 && src > 0 {
+                word |= other.words[src - 1] >> (64 - bit_shift);
+            }
+            self.words[idx] |= word;
+        }
+        // clear anything past capacity in the last word
+        let extra = self.words.len() * 64 - self.capacity;
+        if extra > 0 {
+            let last = self.words.len() - 1;
+            self.words[last] &= u64::MAX >> extra;
+        }
+    }
+
+    /// Smallest sum at or above `at_least`
+    fn first_from(&self, at_least: usize) -> Option<usize> {
+        (at_least..self.capacity).find(|&sum| self.contains(sum))
+    }
+}
+
+/// Directories chosen for deletion and the granularity the sizes were counted in
+pub struct CleanupPlan {
+    pub dirs: Vec<NodeId>,
+    /// 1 when the plan is exact, otherwise sizes were rounded down to this many bytes and the plan may not be the cheapest
+    pub block_size: usize
+}
+
+/// Knapsack over the directory tree: each directory is either deleted whole, skipping everything below it, or kept.
+/// The sums reachable before a run of sibling subtrees follow from the sums reachable after it, so only the rows on
+/// the current path through the tree are ever held, and the choices are recovered by recomputing them.
+struct TreeKnapsack<'a> {
+    tree: &'a FSTree,
+    /// Directories in the subtree of each directory, itself included
+    subtree_dirs: Vec<usize>,
+    block_size: usize,
+    capacity: usize
+}
+
+impl TreeKnapsack<'_> {
+    fn new(tree: &FSTree) -> TreeKnapsack<'_> {
+        let mut subtree_dirs = vec![0; tree.len()];
+        for id in tree.post_order(FSTree::ROOT) {
+            subtree_dirs[id] = 1 + tree.children(id).iter().map(|&child| subtree_dirs[child]).sum::<usize>();
+        }
+        TreeKnapsack { tree, subtree_dirs, block_size: 1, capacity: 1 }
+    }
+
+    fn blocks(&self, dir: NodeId) -> usize {
+        self.tree.dir(dir).tree_size / self.block_size
+    }
+
+    /// Sums freeable from the subtree of `dir` followed by the sums in `after`
+    fn tree_sums(&self, dir: NodeId, after: &SumSet) -> SumSet {
+        let mut sums = self.forest_sums(self.tree.children(dir), after);
+        sums.union_shifted(after, self.blocks(dir));
+        sums
+    }
+
+    /// Sums freeable from the sibling subtrees in order, followed by the sums in `after`
+    fn forest_sums(&self, dirs: &[NodeId], after: &SumSet) -> SumSet {
+        let mut sums = after.clone();
+        for &dir in dirs.iter().rev() {
+            sums = self.tree_sums(dir, &sums);
+        }
+        sums
+    }
+
+    /// Pick directories from the subtrees making up part of `target`, returning the part left for `after`
+    fn choose_forest(&self, dirs: &[NodeId], after: &SumSet, target: usize, chosen: &mut Vec<NodeId>) -> usize {
+        match dirs {
+            [] => target,
+            [dir] => self.choose_tree(*dir, after, target, chosen),
+            _ => {
+                let (first, rest) = dirs.split_at(dirs.len() / 2);
+                let between = self.forest_sums(rest, after);
+                let target = self.choose_forest(first, &between, target, chosen);
+                drop(between);
+                self.choose_forest(rest, after, target, chosen)
+            }
+        }
+    }
+
+    fn choose_tree(&self, dir: NodeId, after: &SumSet, target: usize, chosen: &mut Vec<NodeId>) -> usize {
+        if self.forest_sums(self.tree.children(dir), after).contains(target) {
+            self.choose_forest(self.tree.children(dir), after, target, chosen)
+        }
+        else {
+            chosen.push(dir);
+            target - self.blocks(dir)
+        }
+    }
+
+    /// Most rows held at once while summing a subtree, or a run of siblings
+    fn tree_rows(&self, dir: NodeId) -> usize {
+        self.forest_rows(self.tree.children(dir))
+    }
+
+    fn forest_rows(&self, dirs: &[NodeId]) -> usize {
+        1 + dirs.iter().map(|&dir| self.tree_rows(dir)).max().unwrap_or(0)
+    }
+
+    /// Rows combined while summing a run of siblings, one per directory
+    fn forest_work(&self, dirs: &[NodeId]) -> usize {
+        dirs.iter().map(|&dir| self.subtree_dirs[dir]).sum()
+    }
+
+    /// Rows combined while choosing, following the same splits as `choose_forest`
+    fn choose_work(&self, dirs: &[NodeId]) -> usize {
+        match dirs {
+            [] => 0,
+            [dir] => self.forest_work(self.tree.children(*dir)) + self.choose_work(self.tree.children(*dir)),
+            _ => {
+                let (first, rest) = dirs.split_at(dirs.len() / 2);
+                self.forest_work(rest) + self.choose_work(first) + self.choose_work(rest)
+            }
+        }
+    }
+
+    /// Most rows held at once while choosing, on top of the row the caller passes in
+    fn choose_rows(&self, dirs: &[NodeId]) -> usize {
+        match dirs {
+            [] => 0,
+            [dir] => self.forest_rows(self.tree.children(*dir)).max(self.choose_rows(self.tree.children(*dir))),
+            _ => {
+                let (first, rest) = dirs.split_at(dirs.len() / 2);
+                self.forest_rows(rest).max(1 + self.choose_rows(first)).max(self.choose_rows(rest))
+            }
+        }
+    }
+}
+
+/// Directories to delete, none inside another, freeing at least `needed` bytes while deleting as few bytes as possible
+pub fn plan_cleanup(tree: &FSTree, needed: usize) -> Option<CleanupPlan> {
+    if needed == 0 {
+        return Some(CleanupPlan { dirs: vec![], block_size: 1 });
+    }
+    // the best single directory bounds every useful sum from above
+    let best_single = tree.depth_first(FSTree::ROOT).filter(|&id| tree.dir(id).tree_size >= needed).min_by_key(|&id| tree.dir(id).tree_size)?;
+
+    // exact sums when the rows fit in the budgets, otherwise blocks big enough that they do.
+    // Rounding sizes down keeps every plan found valid, since the real sizes only free more.
+    let mut knapsack = TreeKnapsack::new(tree);
+    let exact_capacity = tree.dir(best_single).tree_size + 1;
+    let rows = 1 + knapsack.forest_rows(&[FSTree::ROOT]).max(knapsack.choose_rows(&[FSTree::ROOT]));
+    let work = knapsack.forest_work(&[FSTree::ROOT]) + knapsack.choose_work(&[FSTree::ROOT]);
+    let block_size = (rows * exact_capacity).div_ceil(CLEANUP_MEMORY_BUDGET * 8).max((work * exact_capacity).div_ceil(CLEANUP_WORK_BUDGET)).max(1);
+    knapsack.block_size = block_size;
+    knapsack.capacity = exact_capacity / block_size + 1;
+
+    let mut nothing = SumSet::new(knapsack.capacity);
+    nothing.insert(0);
+    let mut dirs = vec![];
+    if let Some(target) = knapsack.forest_sums(&[FSTree::ROOT], &nothing).first_from(needed.div_ceil(block_size)) {
+        knapsack.choose_forest(&[FSTree::ROOT], &nothing, target, &mut dirs);
+    }
+
+    // rounding can hide a combination or overshoot, the best single directory is always a valid fallback
+    let freed = dirs.iter().map(|&dir| tree.dir(dir).tree_size).sum::<usize>();
+    if dirs.is_empty() || freed > tree.dir(best_single).tree_size {
+        dirs = vec![best_single];
+    }
+    Some(CleanupPlan { dirs, block_size })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `/a` holds 10 bytes and `/a/a1` with 40, `/b` holds 50 and `/c` 120
+    fn sample_tree() -> FSTree {
+        let mut tree = FSTree::new();
+        let a = tree.add_dir(FSTree::ROOT, "a");
+        tree.add_file(a, "x", 10);
+        let a1 = tree.add_dir(a, "a1");
+        tree.add_file(a1, "y", 40);
+        let b = tree.add_dir(FSTree::ROOT, "b");
+        tree.add_file(b, "z", 50);
+        let c = tree.add_dir(FSTree::ROOT, "c");
+        tree.add_file(c, "w", 120);
+        tree.update_tree_size();
+        tree
+    }
+
+    fn planned_paths(tree: &FSTree, needed: usize) -> Option<Vec<String>> {
+        plan_cleanup(tree, needed).map(|plan| {
+            assert_eq!(plan.block_size, 1);
+            let mut paths = plan.dirs.iter().map(|&dir| tree.path(dir)).collect::<Vec<_>>();
+            paths.sort();
+            paths
+        })
+    }
+
+    #[test]
+    fn siblings_beat_the_best_single_directory() {
+        let tree = sample_tree();
+        assert_eq!(planned_paths(&tree, 100), Some(vec!["/a".to_string(), "/b".to_string()]));
+    }
+
+    #[test]
+    fn nested_directory_combines_with_a_sibling() {
+        let tree = sample_tree();
+        assert_eq!(planned_paths(&tree, 90), Some(vec!["/a/a1".to_string(), "/b".to_string()]));
+    }
+
+    #[test]
+    fn nothing_or_too_much_needed() {
+        let tree = sample_tree();
+        assert_eq!(planned_paths(&tree, 0), Some(vec![]));
+        assert_eq!(planned_paths(&tree, 121), Some(vec!["/a/a1".to_string(), "/c".to_string()]));
+        assert_eq!(planned_paths(&tree, 221), None);
+    }
+}
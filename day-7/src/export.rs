@@ -0,0 +1,120 @@
+use crate::fstree::{FSTree, NodeId};
+
+/// Size the way `du -h` prints it, in powers of 1024
+pub fn human_size(size: usize) -> String {
+    let units = ["", "K", "M", "G", "T"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{}", size)
+    }
+    else if value < 10.0 {
+        format!("{:.1}{}", value, units[unit])
+    }
+    else {
+        format!("{:.0}{}", value, units[unit])
+    }
+}
+
+/// `tree`-style indented listing of a directory with sizes
+pub fn tree_listing(tree: &FSTree, id: NodeId) -> Vec<String> {
+    let mut lines = vec![format!("{} ({})", tree.path(id), tree.dir(id).tree_size)];
+    tree_branch(tree, id, "", &mut lines);
+    lines
+}
+
+fn tree_branch(tree: &FSTree, id: NodeId, prefix: &str, lines: &mut Vec<String>) {
+    let dir = tree.dir(id);
+    let entries = dir.children.len() + dir.files.len();
+    for (idx, &child) in dir.children.iter().enumerate() {
+        let last = idx + 1 == entries;
+        lines.push(format!("{}{}{}/ ({})", prefix, if last { "└── " } else { "├── " }, tree.dir(child).name, tree.dir(child).tree_size));
+        tree_branch(tree, child, &format!("{}{}", prefix, if last { "    " } else { "│   " }), lines);
+    }
+    for (idx, file) in dir.files.iter().enumerate() {
+        let last = dir.children.len() + idx + 1 == entries;
+        lines.push(format!("{}{}{} ({})", prefix, if last { "└── " } else { "├── " }, file.name, file.size));
+    }
+}
+
+/// `du`-style size and path lines, each directory after its children
+pub fn du_lines(tree: &FSTree, id: NodeId, human: bool) -> Vec<String> {
+    tree.post_order(id).into_iter().map(|dir| {
+        let size = tree.dir(dir).tree_size;
+        format!("{}\t{}", if human { human_size(size) } else { size.to_string() }, tree.path(dir))
+    }).collect()
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c)
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+/// Nested JSON document of directories, their files and sizes
+pub fn json_document(tree: &FSTree, id: NodeId) -> String {
+    let mut out = String::new();
+    json_dir(tree, id, 0, &mut out);
+    out
+}
+
+fn json_dir(tree: &FSTree, id: NodeId, depth: usize, out: &mut String) {
+    let dir = tree.dir(id);
+    let pad = "  ".repeat(depth + 1);
+    let name = if dir.parent.is_none() { "/" } else { dir.name.as_str() };
+    out.push_str("{\n");
+    out.push_str(&format!("{}\"name\": {},\n", pad, json_string(name)));
+    out.push_str(&format!("{}\"path\": {},\n", pad, json_string(&tree.path(id))));
+    out.push_str(&format!("{}\"size\": {},\n", pad, dir.tree_size));
+    out.push_str(&format!("{}\"file_size\": {},\n", pad, dir.file_size));
+
+    let files = dir.files.iter().map(|file| format!("{}  {{\"name\": {}, \"size\": {}}}", pad, json_string(&file.name), file.size)).collect::<Vec<_>>();
+    if files.is_empty() {
+        out.push_str(&format!("{}\"files\": [],\n", pad));
+    }
+    else {
+        out.push_str(&format!("{}\"files\": [\n{}\n{}],\n", pad, files.join(",\n"), pad));
+    }
+
+    if dir.children.is_empty() {
+        out.push_str(&format!("{}\"dirs\": []\n", pad));
+    }
+    else {
+        out.push_str(&format!("{}\"dirs\": [\n", pad));
+        for (idx, &child) in dir.children.iter().enumerate() {
+            out.push_str(&format!("{}  ", pad));
+            json_dir(tree, child, depth + 2, out);
+            out.push_str(if idx + 1 < dir.children.len() { ",\n" } else { "\n" });
+        }
+        out.push_str(&format!("{}]\n", pad));
+    }
+    out.push_str(&format!("{}}}", "  ".repeat(depth)));
+}
+
+/// Folded stacks (`/;dir;file size`) for flamegraph tooling, one line per file
+pub fn folded_stacks(tree: &FSTree, id: NodeId) -> Vec<String> {
+    let mut lines = vec![];
+    for dir in tree.depth_first(id) {
+        let mut frames = std::iter::once(dir).chain(tree.ancestors(dir)).map(|frame| {
+            if tree.parent(frame).is_none() { "/".to_string() } else { tree.dir(frame).name.replace(";", "_") }
+        }).collect::<Vec<_>>();
+        frames.reverse();
+        let stack = frames.join(";");
+        for file in tree.dir(dir).files.iter() {
+            lines.push(format!("{};{} {}", stack, file.name.replace(";", "_"), file.size));
+        }
+    }
+    lines
+}
@@ -0,0 +1,217 @@
+use std::collections::VecDeque;
+
+/// Index of a directory in the tree's arena
+pub type NodeId = usize;
+
+pub struct FSFile {
+    pub name: String,
+    pub size: usize
+}
+
+pub struct FSDir {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>,
+    pub files: Vec<FSFile>,
+    pub file_size: usize,
+    pub tree_size: usize
+}
+
+/// Directory tree stored as an arena, directories refer to each other by `NodeId`
+pub struct FSTree {
+    dirs: Vec<FSDir>
+}
+
+impl FSTree {
+    pub const ROOT: NodeId = 0;
+
+    pub fn new() -> Self {
+        FSTree { dirs: vec![FSDir { name: "".to_string(), parent: None, children: vec![], files: vec![], file_size: 0, tree_size: 0 }] }
+    }
+
+    /// Directories in the arena, including removed ones, so every `NodeId` is below it
+    pub fn len(&self) -> usize {
+        self.dirs.len()
+    }
+
+    pub fn dir(&self, id: NodeId) -> &FSDir {
+        &self.dirs[id]
+    }
+
+    /// Child directory with the given name
+    pub fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        self.dirs[id].children.iter().copied().find(|&child| self.dirs[child].name == name)
+    }
+
+    pub fn children(&self, id: NodeId) -> &[NodeId] {
+        &self.dirs[id].children
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.dirs[id].parent
+    }
+
+    /// Add a directory under `parent` if it does not exist, returning its id either way
+    pub fn add_dir(&mut self, parent: NodeId, name: &str) -> NodeId {
+        if let Some(existing) = self.child(parent, name) {
+            return existing;
+        }
+        let id = self.dirs.len();
+        self.dirs.push(FSDir { name: name.to_string(), parent: Some(parent), children: vec![], files: vec![], file_size: 0, tree_size: 0 });
+        self.dirs[parent].children.push(id);
+        id
+    }
+
+    pub fn add_file(&mut self, dir: NodeId, name: &str, size: usize) {
+        let cur_dir = &mut self.dirs[dir];
+        cur_dir.files.push(FSFile { name: name.to_string(), size });
+        cur_dir.file_size += size;
+    }
+
+    /// Size of a file directly in `dir`
+    pub fn file_size(&self, dir: NodeId, name: &str) -> Option<usize> {
+        self.dirs[dir].files.iter().find(|file| file.name == name).map(|file| file.size)
+    }
+
+    /// Change the size of a listed file before tree sizes are reconciled
+    pub fn resize_file(&mut self, dir: NodeId, name: &str, size: usize) {
+        let cur_dir = &mut self.dirs[dir];
+        if let Some(file) = cur_dir.files.iter_mut().find(|file| file.name == name) {
+            cur_dir.file_size = cur_dir.file_size - file.size + size;
+            file.size = size;
+        }
+    }
+
+    /// Detach a directory and everything under it, shrinking every ancestor, and return the bytes freed
+    pub fn remove_dir(&mut self, id: NodeId) -> usize {
+        let parent = match self.parent(id) {
+            Some(parent) => parent,
+            None => return 0 // the root stays
+        };
+        self.dirs[parent].children.retain(|&child| child != id);
+        let freed = self.dirs[id].tree_size;
+        self.shrink_ancestors(id, freed);
+        freed
+    }
+
+    /// Remove a file from a directory, returning its size if it was there
+    pub fn remove_file(&mut self, dir: NodeId, name: &str) -> Option<usize> {
+        let pos = self.dirs[dir].files.iter().position(|file| file.name == name)?;
+        let size = self.dirs[dir].files.remove(pos).size;
+        self.dirs[dir].file_size -= size;
+        self.dirs[dir].tree_size -= size;
+        self.shrink_ancestors(dir, size);
+        Some(size)
+    }
+
+    fn shrink_ancestors(&mut self, id: NodeId, size: usize) {
+        let ancestors = self.ancestors(id).collect::<Vec<_>>();
+        for ancestor in ancestors {
+            self.dirs[ancestor].tree_size -= size;
+        }
+    }
+
+    /// Parent, grandparent and so on up to the root
+    pub fn ancestors(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        std::iter::successors(self.parent(id), move |&ancestor| self.parent(ancestor))
+    }
+
+    /// Absolute path of a directory, `/` for the root
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names = std::iter::once(id).chain(self.ancestors(id)).map(|dir| self.dirs[dir].name.as_str()).collect::<Vec<_>>();
+        names.reverse();
+        match names.len() {
+            1 => "/".to_string(),
+            _ => names.join("/")
+        }
+    }
+
+    pub fn file_path(&self, dir: NodeId, name: &str) -> String {
+        match self.parent(dir) {
+            Some(_) => format!("{}/{}", self.path(dir), name),
+            None => format!("/{}", name)
+        }
+    }
+
+    /// Directory at an absolute path, or relative to `from`, understanding `.` and `..`
+    pub fn resolve(&self, from: NodeId, path: &str) -> Option<NodeId> {
+        let mut cur = if path.starts_with("/") { FSTree::ROOT } else { from };
+        for part in path.split("/").filter(|part| !part.is_empty()) {
+            cur = match part {
+                "." => cur,
+                ".." => self.parent(cur).unwrap_or(FSTree::ROOT),
+                name => self.child(cur, name)?
+            };
+        }
+        Some(cur)
+    }
+
+    /// Total size of everything under a path
+    pub fn get_tree_size(&self, path: &str) -> Option<usize> {
+        self.resolve(FSTree::ROOT, path).map(|id| self.dirs[id].tree_size)
+    }
+
+    /// Depth-first reconciliation of tree_size for each directory
+    pub fn update_tree_size(&mut self) {
+        // children always come after their parent in depth-first order, so sum in reverse
+        let order = self.depth_first(FSTree::ROOT).collect::<Vec<_>>();
+        for &id in order.iter().rev() {
+            let children_size = self.dirs[id].children.iter().map(|&child| self.dirs[child].tree_size).sum::<usize>();
+            self.dirs[id].tree_size = self.dirs[id].file_size + children_size;
+        }
+    }
+
+    /// Directories under and including `from`, each before its children
+    pub fn depth_first(&self, from: NodeId) -> DepthFirst<'_> {
+        DepthFirst { tree: self, stack: vec![from] }
+    }
+
+    /// Directories under and including `from`, each after its children, like `du` prints them
+    pub fn post_order(&self, from: NodeId) -> Vec<NodeId> {
+        // a pre-order walk visiting the last child first, reversed
+        let mut order = vec![];
+        let mut stack = vec![from];
+        while let Some(id) = stack.pop() {
+            order.push(id);
+            stack.extend(self.children(id));
+        }
+        order.reverse();
+        order
+    }
+
+    /// Directories under and including `from`, level by level
+    pub fn breadth_first(&self, from: NodeId) -> BreadthFirst<'_> {
+        BreadthFirst { tree: self, queue: VecDeque::from([from]) }
+    }
+}
+
+pub struct DepthFirst<'a> {
+    tree: &'a FSTree,
+    stack: Vec<NodeId>
+}
+
+impl Iterator for DepthFirst<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.stack.pop()?;
+        // push in reverse so children come out in listing order
+        self.stack.extend(self.tree.children(id).iter().rev());
+        Some(id)
+    }
+}
+
+pub struct BreadthFirst<'a> {
+    tree: &'a FSTree,
+    queue: VecDeque<NodeId>
+}
+
+impl Iterator for BreadthFirst<'_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let id = self.queue.pop_front()?;
+        self.queue.extend(self.tree.children(id));
+        Some(id)
+    }
+}
@@ -1,194 +1,407 @@
-use std::{io::{self}, collections::HashMap, vec, borrow::Borrow};
+use std::{collections::{BTreeSet, HashMap}, env, fs, io::{self}, path::Path};
+
+mod check;
+mod cleanup;
+mod export;
+mod fstree;
+mod shell;
+mod transcript;
+
+use fstree::{FSTree, NodeId};
+use shell::Shell;
+use transcript::{RealDir, Rng, transcript};
+use check::{Confidence, Issue, size_confidence};
+use cleanup::plan_cleanup;
+
+/// Disk geometry for the cleanup parts, overridable with `--total` and `--needed`
+struct DiskConfig {
+    total_disk_space: usize,
+    free_space_needed: usize
+}
 
+impl DiskConfig {
+    const TOTAL_DISK_SPACE: usize = 70000000;
+    const FREE_SPACE_NEEDED: usize = 30000000;
 
-struct FSFile {
-    name: String,
-    size: usize
-}
+    /// Pull the size options out of the arguments, leaving the rest in place
+    fn from_args(args: &mut Vec<String>) -> DiskConfig {
+        let mut config = DiskConfig { total_disk_space: DiskConfig::TOTAL_DISK_SPACE, free_space_needed: DiskConfig::FREE_SPACE_NEEDED };
+        while let Some(pos) = args.iter().position(|arg| arg == "--total" || arg == "--needed") {
+            let value = args.get(pos + 1).and_then(|v| v.parse::<usize>().ok()).unwrap_or_else(|| panic!("{} needs a size in bytes", args[pos]));
+            match args[pos].as_str() {
+                "--total" => config.total_disk_space = value,
+                _ => config.free_space_needed = value
+            }
+            args.drain(pos..pos + 2);
+        }
+        config
+    }
 
-struct FSDir {
-    name: String,
-    files: Vec<FSFile>,
-    child_dirs: Vec<String>,
-    file_size: usize,
-    tree_size: usize
+    /// Bytes that still have to be freed for a disk currently holding `used`
+    fn still_needed(&self, used: usize) -> usize {
+        self.free_space_needed.saturating_sub(self.total_disk_space.saturating_sub(used))
+    }
 }
 
 struct FSParser {
     lines: Vec<String>,
     idx: usize,
-    dirs: HashMap<String, FSDir>,
-    pwd: Vec<String>
+    tree: FSTree,
+    cwd: NodeId,
+    /// Names shown by the listings of each directory listed at least once
+    listed: HashMap<NodeId, BTreeSet<String>>,
+    issues: Vec<Issue>
 }
 
 impl FSParser {
     fn new(lines: Vec<String>) -> Self {
-        return FSParser { lines: lines, idx: 0, dirs: HashMap::new(), pwd: vec![] };
+        FSParser { lines, idx: 0, tree: FSTree::new(), cwd: FSTree::ROOT, listed: HashMap::new(), issues: vec![] }
     }
 
     fn parse(mut self) -> Self {
-        self.dirs.insert("/".to_string(), FSDir { name: "".to_string(), files: vec![], child_dirs: vec![], file_size: 0, tree_size: 0 });
         while self.idx < self.lines.len() {
             self.apply_commands();
         };
 
+        let never_listed = self.tree.depth_first(FSTree::ROOT).filter(|id| !self.listed.contains_key(id)).collect::<Vec<_>>();
+        self.issues.extend(never_listed.into_iter().map(|dir| Issue::NeverListed { dir }));
+
         // update subdirectory sizes, depth-first
-        self.update_tree_size("/");
+        self.tree.update_tree_size();
 
         self
     }
 
-    fn get_tree_size(&self, path: &str) -> usize {
-        let root = self.dirs.get("/").unwrap().tree_size;
-        return 0;
-    }
-
-    /// Depth-first reconciliation of tree_size for each directory
-    fn update_tree_size(&mut self, path: &str) -> usize {
-        //let mut cur_dir = self.dirs.get_mut(path).unwrap();
-        let mut tree_size = 0;
-
-        for subdir in self.dirs.get_mut(path).unwrap().child_dirs.clone() {
-            let child_path = join_path(path, subdir.as_str());
-            tree_size = tree_size + self.update_tree_size(child_path.as_str());
-        }
-
-        let mut cur_dir = self.dirs.get_mut(path).unwrap();
-        cur_dir.tree_size = tree_size + cur_dir.file_size;
-
-        return cur_dir.tree_size;
-    }
-
-    fn get_pwd(&mut self) -> String {
-        let mut root = String::from("/");
-        let s = self.pwd.join("/");
-        root.push_str(s.as_str());
-
-        return root;
-    }
-
-    /// Add a directory if it does not exist
-    fn add_dir(&mut self, name: &str) {
-        let pwd = self.get_pwd().to_string();
-        let path = join_path(pwd.as_str(), name);
-        if !self.dirs.contains_key(&path) {
-            self.dirs.insert(path, FSDir { name: name.to_string(), files: vec![], child_dirs: vec![], file_size: 0, tree_size: 0 });
-        
-            // add child_dir in parent
-            self.dirs.get_mut(&pwd).unwrap().child_dirs.push(name.to_string());
-        }
-    }
-
     /// Apply commands
     fn apply_commands(&mut self) {
         let cur_line = self.lines[self.idx].to_string();
-        if cur_line.to_string().starts_with("$ cd ") {
+        let line = self.idx + 1;
+        if cur_line.starts_with("$ cd ") {
             let next_dir = cur_line.strip_prefix("$ cd ").unwrap();
-            self.cwd(next_dir);
-            self.idx = self.idx + 1;
+            self.cd(next_dir, line);
+            self.idx += 1;
         }
         // dir listing
-        if cur_line.to_string().starts_with("$ ls") {
-            self.idx = self.idx + 1;
-            self.ls_dir()
+        else if cur_line.starts_with("$ ls") {
+            self.idx += 1;
+            self.ls_dir(line)
+        }
+        else {
+            if cur_line.starts_with("$") {
+                self.issues.push(Issue::UnknownCommand { line, text: cur_line });
+            } else if !cur_line.trim().is_empty() {
+                self.issues.push(Issue::StrayOutput { line, text: cur_line });
+            }
+            self.idx += 1;
         }
-
     }
 
-    fn ls_dir(&mut self) {
-        'ls: loop {
-            let pwd = self.get_pwd();
-            if self.idx >= self.lines.len() || self.lines[self.idx].starts_with("$") {
-                return;
-            }
-            let mut ls_entry = self.lines[self.idx].split(" ");
-            match ls_entry.next().unwrap() {
+    /// Read a listing of the working directory, merging it with any earlier listing of the same directory
+    fn ls_dir(&mut self, ls_line: usize) {
+        // only what earlier listings showed counts, not directories that exist because of a cd
+        let earlier_listing = self.listed.get(&self.cwd).cloned();
+        let issues_before = self.issues.len();
+        let mut seen = BTreeSet::new();
+        while self.idx < self.lines.len() && !self.lines[self.idx].starts_with("$") {
+            let line = self.idx + 1;
+            let entry = self.lines[self.idx].to_string();
+            self.idx += 1;
+            let (kind, name) = match entry.split_once(" ") {
+                Some(parts) => parts,
+                None => {
+                    self.issues.push(Issue::MalformedEntry { line, text: entry });
+                    continue;
+                }
+            };
+            match kind {
                 "dir" => {
-                    let dir_name = ls_entry.next().unwrap();
-                    self.add_dir(dir_name.to_string().as_str());
-                }, 
-                file_size_str => {
-                    let file_size = file_size_str.parse::<usize>().unwrap();
-                    let file_name = ls_entry.next().unwrap();
-                    let f = FSFile{ name: file_name.to_string(), size: file_size };
-                    let mut cur_dir = self.dirs.get_mut(&pwd).unwrap();
-                    cur_dir.files.push(f);
-                    cur_dir.file_size = cur_dir.file_size + file_size;
+                    self.tree.add_dir(self.cwd, name);
+                },
+                file_size_str => match (file_size_str.parse::<usize>(), self.tree.file_size(self.cwd, name)) {
+                    (Err(_), _) => {
+                        self.issues.push(Issue::MalformedEntry { line, text: entry });
+                        continue;
+                    },
+                    (Ok(file_size), None) => self.tree.add_file(self.cwd, name, file_size),
+                    (Ok(file_size), Some(earlier)) => {
+                        if file_size != earlier {
+                            self.issues.push(Issue::ConflictingSize { line, dir: self.cwd, name: name.to_string(), earlier, later: file_size });
+                            self.tree.resize_file(self.cwd, name, file_size);
+                        }
+                    }
                 }
+            };
+            if earlier_listing.as_ref().is_some_and(|earlier| !earlier.contains(name)) {
+                self.issues.push(Issue::AddedInRelisting { line, dir: self.cwd, name: name.to_string() });
             }
-            self.idx = self.idx + 1;
+            seen.insert(name.to_string());
         }
+
+        if let Some(earlier) = earlier_listing {
+            let missing = earlier.into_iter().filter(|name| !seen.contains(name)).collect::<Vec<_>>();
+            self.issues.extend(missing.into_iter().map(|name| Issue::MissingFromRelisting { line: ls_line, dir: self.cwd, name }));
+            if self.issues.len() == issues_before {
+                self.issues.push(Issue::RepeatedListing { line: ls_line, dir: self.cwd });
+            }
+        }
+        self.listed.entry(self.cwd).or_default().extend(seen);
     }
 
-    /// Change working directory
-    fn cwd(&mut self, dir: &str) {
-        if dir == "/" {
-            self.pwd.clear();
+    /// Change working directory, creating directories no listing has shown yet
+    fn cd(&mut self, dir: &str, line: usize) {
+        self.cwd = match dir {
+            "/" => FSTree::ROOT,
+            ".." => self.tree.parent(self.cwd).unwrap_or_else(|| {
+                self.issues.push(Issue::AboveRoot { line });
+                FSTree::ROOT
+            }),
+            name => match self.tree.child(self.cwd, name) {
+                Some(child) => child,
+                None => {
+                    let child = self.tree.add_dir(self.cwd, name);
+                    self.issues.push(Issue::UnknownDir { line, dir: child, parent_listed: self.listed.contains_key(&self.cwd) });
+                    child
+                }
+            }
+        };
+    }
+}
+
+/// Answer a query about the parsed tree: size, children, ancestors or a dfs/bfs walk of a path
+fn query(tree: &FSTree, command: &str, path: &str) {
+    let id = match tree.resolve(FSTree::ROOT, path) {
+        Some(id) => id,
+        None => {
+            println!("No such directory: {}", path);
+            return;
         }
-        else if dir == ".." {
-            if self.pwd.len() > 0 {
-                self.pwd.pop();
+    };
+    match command {
+        "size" => println!("{} {}", tree.dir(id).tree_size, tree.path(id)),
+        "children" => {
+            for &child in tree.children(id) {
+                println!("dir {} ({})", tree.dir(child).name, tree.dir(child).tree_size);
             }
+            for file in tree.dir(id).files.iter() {
+                println!("{} {}", file.size, file.name);
+            }
+        },
+        "ancestors" => {
+            for ancestor in tree.ancestors(id) {
+                println!("{} {}", tree.dir(ancestor).tree_size, tree.path(ancestor));
+            }
+        },
+        "dfs" | "bfs" => {
+            let order: Vec<NodeId> = match command {
+                "dfs" => tree.depth_first(id).collect(),
+                _ => tree.breadth_first(id).collect()
+            };
+            for dir in order {
+                println!("{} {}", tree.dir(dir).tree_size, tree.path(dir));
+            }
+        },
+        _ => println!("Unknown query: {}", command)
+    }
+}
+
+/// Write the whole disk usage as a tree listing, du lines, JSON or folded stacks
+fn export(tree: &FSTree, format: &str, human: bool) {
+    let lines = match format {
+        "tree" => export::tree_listing(tree, FSTree::ROOT),
+        "du" => export::du_lines(tree, FSTree::ROOT, human),
+        "json" => vec![export::json_document(tree, FSTree::ROOT)],
+        "folded" => export::folded_stacks(tree, FSTree::ROOT),
+        _ => {
+            println!("Unknown export format: {}, expected tree, du, json or folded", format);
+            return;
         }
-        else {
-            self.pwd.push(dir.to_string());
+    };
+    for line in lines {
+        println!("{}", line);
+    }
+}
+
+/// Cheapest set of non-nested directories whose deletion frees the needed space
+fn cleanup(tree: &FSTree, config: &DiskConfig) {
+    let used = tree.dir(FSTree::ROOT).tree_size;
+    let needed = config.still_needed(used);
+    println!("Cleanup plan\r\n{}", "-".repeat(10));
+    println!("Disk size: {}, used: {}, free space needed: {}", config.total_disk_space, used, config.free_space_needed);
+    println!("Additional space needed: {}\n", needed);
+
+    match plan_cleanup(tree, needed) {
+        Some(plan) => {
+            let dirs = plan.dirs;
+            for &dir in dirs.iter() {
+                println!("Delete dir {} ({})", tree.path(dir), tree.dir(dir).tree_size);
+            }
+            let freed = dirs.iter().map(|&dir| tree.dir(dir).tree_size).sum::<usize>();
+            println!("\nDirectories deleted: {}, bytes deleted: {}, over the need by {}", dirs.len(), freed, freed - needed);
+            if plan.block_size > 1 {
+                println!("Sizes were counted in blocks of {} bytes to keep the search within its memory and time budgets, a cheaper plan may exist", plan.block_size);
+            }
+        },
+        None => println!("Even deleting everything cannot free {} bytes", needed)
+    }
+}
+
+/// Report every inconsistency in the transcript and how far the computed sizes can be trusted
+fn check(parser: &FSParser) {
+    let tree = &parser.tree;
+    println!("Transcript check\r\n{}", "-".repeat(10));
+    for issue in parser.issues.iter() {
+        match issue.line() {
+            Some(line) => println!("line {}: {}", line, issue.describe(tree)),
+            None => println!("{}", issue.describe(tree))
         }
     }
+    if parser.issues.is_empty() {
+        println!("No issues found");
+    }
 
-    /// Get parent path from current path
-    fn parent_path(&self, path: &str) -> String {
-        if path.len() <= 1 {
-            return "/".to_string();
+    let confidence = size_confidence(tree, &parser.listed, &parser.issues);
+    let count = |level: Confidence| confidence.iter().filter(|&&(_, c)| c == level).count();
+    println!("\nDirectories: {}, exact: {}, conflicted: {}, lower bound: {}", confidence.len(), count(Confidence::Exact), count(Confidence::Conflicted), count(Confidence::LowerBound));
+    let root_confidence = confidence.iter().find(|&&(id, _)| id == FSTree::ROOT).map(|&(_, c)| c).unwrap();
+    println!("Root size {} is {}", tree.dir(FSTree::ROOT).tree_size, root_confidence.describe());
+
+    let doubtful = confidence.iter().filter(|&&(_, c)| c != Confidence::Exact).collect::<Vec<_>>();
+    if !doubtful.is_empty() {
+        println!();
+        for &&(id, c) in doubtful.iter() {
+            println!("{:<12} {:>10} {}", c.describe(), tree.dir(id).tree_size, tree.path(id));
         }
-        let parent_slash = path.rfind("/").unwrap();
-        let slash = path.len()-parent_slash;
-        return path[..slash - 1].to_string();
     }
 }
 
-fn join_path(parent: &str, name: &str) -> String {
-    let mut p = parent.to_string();
-    if !p.ends_with("/") {
-        p.push_str("/");
+/// Parse a transcript generated from a real directory and check every computed size against the disk
+fn compare(real_root: &RealDir, lines: Vec<String>) {
+    let parser = FSParser::new(lines).parse();
+    let tree = &parser.tree;
+    println!("Compare with disk\r\n{}", "-".repeat(10));
+    let real_sizes = real_root.sizes("/");
+    let mut mismatches = 0;
+    for (path, real_size) in real_sizes.iter() {
+        match tree.get_tree_size(path) {
+            Some(size) if size == *real_size => (),
+            Some(size) => {
+                mismatches += 1;
+                println!("{} computed {} but is {} on disk", path, size, real_size);
+            },
+            None => {
+                mismatches += 1;
+                println!("{} is missing from the parsed tree", path);
+            }
+        }
+    }
+    let parsed_dirs = tree.depth_first(FSTree::ROOT).count();
+    if parsed_dirs != real_sizes.len() {
+        println!("Parsed {} directories but the disk has {}", parsed_dirs, real_sizes.len());
     }
-    p.push_str(name);
-    return p;
+    println!("Directories: {}, mismatches: {}, transcript issues: {}", real_sizes.len(), mismatches, parser.issues.len());
+    println!("Root size: {}", tree.dir(FSTree::ROOT).tree_size);
 }
 
 fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let config = DiskConfig::from_args(&mut args);
+    if args.first().map(|s| s.as_str()) == Some("shell") {
+        // The transcript comes from a file so stdin is free for shell commands
+        let path = args.get(1).expect("shell needs the transcript file");
+        let lines = fs::read_to_string(path).expect("cannot read transcript").lines().map(|line| line.to_string()).collect();
+        Shell::new(FSParser::new(lines).parse().tree, config.total_disk_space).run();
+        return;
+    }
+
+    if let Some(mode @ ("generate" | "compare")) = args.first().map(|s| s.as_str()) {
+        // Walk a real directory, optionally in an order shuffled by the seed
+        let path = args.get(1).unwrap_or_else(|| panic!("{} needs a directory", mode));
+        let real_root = RealDir::read(Path::new(path)).unwrap_or_else(|err| panic!("cannot read {}: {}", path, err));
+        let mut rng = args.get(2).map(|seed| Rng::new(seed.parse::<u64>().expect("seed must be a number")));
+        let lines = transcript(&real_root, rng.as_mut());
+        match mode {
+            "generate" => lines.iter().for_each(|line| println!("{}", line)),
+            _ => compare(&real_root, lines)
+        }
+        return;
+    }
+
     let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lines().flatten().collect();
+    let lines: Vec<String> = stdin.lines().map_while(Result::ok).collect();
+
+    let parser = FSParser::new(lines).parse();
+    let tree = &parser.tree;
+
+    match args.first().map(|s| s.as_str()) {
+        Some("check") => {
+            check(&parser);
+            return;
+        },
+        Some("cleanup") => {
+            cleanup(tree, &config);
+            return;
+        },
+        Some("export") => {
+            export(tree, args.get(1).map(|s| s.as_str()).unwrap_or("tree"), args.get(2).map(|s| s.as_str()) == Some("-h"));
+            return;
+        },
+        Some(command) => {
+            query(tree, command, args.get(1).map(|s| s.as_str()).unwrap_or("/"));
+            return;
+        },
+        None => ()
+    }
 
     println!("Part 1\r\n{}", "-".repeat(10));
 
-    let mut parser = FSParser::new(lines).parse();
-
-    let mut filtered_tree_size = parser.dirs.iter().filter(|(_path, cur_dir)| cur_dir.tree_size <= 100000).fold(0, |acc,(_path, cur_dir)| {
-        acc + cur_dir.tree_size
-    });
+    let filtered_tree_size = tree.depth_first(FSTree::ROOT).map(|id| tree.dir(id).tree_size).filter(|&tree_size| tree_size <= 100000).sum::<usize>();
     println!("Total size dirs under 100kB: {}", filtered_tree_size);
 
-    let mut root_tree_size = parser.dirs.iter().filter(|c| c.0.to_string() == "/".to_string()).fold(0, |acc,(_path, cur_dir)| {
-        acc + cur_dir.tree_size
-    });
-  
+    let root_tree_size = tree.get_tree_size("/").unwrap();
 
     println!("Part 2\r\n{}", "-".repeat(10));
 
-    const FREE_SPACE_NEEDED: usize = 30000000;
-    const TOTAL_DISK_SPACE: usize = 70000000;
-    let free_space_avail = TOTAL_DISK_SPACE - root_tree_size;
-    let free_space_needed = FREE_SPACE_NEEDED - free_space_avail;
+    let free_space_avail = config.total_disk_space.saturating_sub(root_tree_size);
+    let free_space_needed = config.still_needed(root_tree_size);
     println!("Free space available: {}", free_space_avail);
     println!("Additional space needed: {}", free_space_needed);
 
-    let mut candidate_dirs = parser.dirs.iter().filter(|&(path, cur_dir)| cur_dir.tree_size >= free_space_needed).map(|c| {
-        return (c.0.to_string(), c.1.tree_size);
-    }).collect::<Vec<_>>();
-    candidate_dirs.sort_by_key(|c| c.1);
-    let smallest_dir_to_delete = candidate_dirs.iter().nth(0).unwrap();
+    match tree.depth_first(FSTree::ROOT).filter(|&id| tree.dir(id).tree_size >= free_space_needed).min_by_key(|&id| tree.dir(id).tree_size) {
+        Some(smallest_dir_to_delete) => println!("Delete dir {} which has a total size of {}", tree.path(smallest_dir_to_delete), tree.dir(smallest_dir_to_delete).tree_size),
+        None => println!("Even deleting everything cannot free {} bytes", free_space_needed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    println!("Delete dir {} which has a total size of {}", smallest_dir_to_delete.0, smallest_dir_to_delete.1);
+    fn parse(transcript: &[&str]) -> FSParser {
+        FSParser::new(transcript.iter().map(|line| line.to_string()).collect()).parse()
+    }
+
+    fn descriptions(parser: &FSParser) -> Vec<String> {
+        parser.issues.iter().map(|issue| issue.describe(&parser.tree)).collect()
+    }
 
-   
-}
\ No newline at end of file
+    #[test]
+    fn relisting_ignores_directories_only_known_from_cd() {
+        let parser = parse(&["$ cd /", "$ ls", "dir a", "$ cd b", "$ cd ..", "$ ls", "dir a"]);
+        let issues = descriptions(&parser);
+        assert!(!issues.iter().any(|issue| issue.contains("missing from a later listing")), "{:?}", issues);
+        assert!(issues.contains(&"cd into /b which the listing of its parent does not show".to_string()));
+        assert!(issues.contains(&"/ listed again with the same contents, ignored".to_string()));
+    }
+
+    #[test]
+    fn relisting_reports_changed_entries() {
+        let parser = parse(&["$ cd /", "$ ls", "dir a", "100 x", "$ ls", "120 x", "5 y"]);
+        assert_eq!(descriptions(&parser), [
+            "/x listed as 120 bytes after 100 earlier, keeping 120",
+            "y appears in a later listing of / only",
+            "a missing from a later listing of /",
+            "/a was never listed"
+        ]);
+        assert_eq!(parser.tree.dir(FSTree::ROOT).tree_size, 125);
+    }
+}
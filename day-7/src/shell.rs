@@ -0,0 +1,173 @@
+use std::io::{self, BufRead, Write};
+
+use crate::{export::{du_lines, tree_listing}, fstree::{FSTree, NodeId}};
+
+/// Interactive exploration of the reconstructed disk
+pub struct Shell {
+    tree: FSTree,
+    cwd: NodeId,
+    total_disk_space: usize
+}
+
+impl Shell {
+    pub fn new(tree: FSTree, total_disk_space: usize) -> Self {
+        Shell { tree, cwd: FSTree::ROOT, total_disk_space }
+    }
+
+    /// Read commands from stdin until `exit` or end of input
+    pub fn run(&mut self) {
+        println!("Commands: cd, ls, pwd, du [-h], tree, find [path] -size +N|-N, rm [-r], df, exit\n");
+        let stdin = io::stdin();
+        loop {
+            print!("{}$ ", self.tree.path(self.cwd));
+            io::stdout().flush().unwrap();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                println!();
+                return;
+            }
+            let args = line.split_ascii_whitespace().collect::<Vec<_>>();
+            match args.first() {
+                Some(&"exit") | Some(&"quit") => return,
+                Some(&command) => self.execute(command, &args[1..]),
+                None => ()
+            }
+        }
+    }
+
+    fn execute(&mut self, command: &str, args: &[&str]) {
+        let flags = args.iter().filter(|arg| arg.starts_with("-")).copied().collect::<Vec<_>>();
+        let operands = args.iter().filter(|arg| !arg.starts_with("-") && !arg.starts_with("+")).copied().collect::<Vec<_>>();
+        let target = operands.first().copied().unwrap_or(".");
+        match command {
+            "pwd" => println!("{}", self.tree.path(self.cwd)),
+            "cd" => match self.tree.resolve(self.cwd, operands.first().copied().unwrap_or("/")) {
+                Some(dir) => self.cwd = dir,
+                None => println!("cd: no such directory: {}", target)
+            },
+            "ls" => {
+                if let Some(dir) = self.dir_arg("ls", target) {
+                    for &child in self.tree.children(dir) {
+                        println!("dir {}", self.tree.dir(child).name);
+                    }
+                    for file in self.tree.dir(dir).files.iter() {
+                        println!("{} {}", file.size, file.name);
+                    }
+                }
+            },
+            "du" => {
+                if let Some(dir) = self.dir_arg("du", target) {
+                    for line in du_lines(&self.tree, dir, flags.contains(&"-h")) {
+                        println!("{}", line);
+                    }
+                }
+            },
+            "tree" => {
+                if let Some(dir) = self.dir_arg("tree", target) {
+                    for line in tree_listing(&self.tree, dir) {
+                        println!("{}", line);
+                    }
+                }
+            },
+            "find" => self.find(args),
+            "rm" => self.rm(operands.first().copied(), flags.contains(&"-r") || flags.contains(&"-rf")),
+            "df" => {
+                let used = self.tree.dir(FSTree::ROOT).tree_size;
+                println!("Size {}  Used {}  Avail {}", self.total_disk_space, used, self.total_disk_space.saturating_sub(used));
+            },
+            _ => println!("{}: command not found", command)
+        }
+    }
+
+    fn dir_arg(&self, command: &str, path: &str) -> Option<NodeId> {
+        let dir = self.tree.resolve(self.cwd, path);
+        if dir.is_none() {
+            println!("{}: no such directory: {}", command, path);
+        }
+        dir
+    }
+
+    /// `find [path] -size +N` lists directories and files bigger than N, `-N` smaller
+    fn find(&self, args: &[&str]) {
+        let size_pos = args.iter().position(|&arg| arg == "-size");
+        let bound = size_pos.and_then(|pos| args.get(pos + 1)).and_then(|bound| {
+            let (larger, digits) = match bound.chars().next()? {
+                '+' => (Some(true), &bound[1..]),
+                '-' => (Some(false), &bound[1..]),
+                _ => (None, &bound[..])
+            };
+            digits.parse::<usize>().ok().map(|n| (larger, n))
+        });
+        let (larger, n) = match (size_pos, bound) {
+            (None, _) => (Some(true), 0),
+            (Some(_), Some(bound)) => bound,
+            (Some(_), None) => {
+                println!("find: -size needs a number such as +100000");
+                return;
+            }
+        };
+        let matches = |size: usize| match larger {
+            Some(true) => size > n,
+            Some(false) => size < n,
+            None => size == n
+        };
+
+        let path = match args.first() {
+            Some(path) if !path.starts_with("-") => path,
+            _ => "."
+        };
+        let dir = match self.dir_arg("find", path) {
+            Some(dir) => dir,
+            None => return
+        };
+        for id in self.tree.depth_first(dir) {
+            let cur_dir = self.tree.dir(id);
+            if matches(cur_dir.tree_size) {
+                println!("{}\t{}", cur_dir.tree_size, self.tree.path(id));
+            }
+            for file in cur_dir.files.iter().filter(|file| matches(file.size)) {
+                println!("{}\t{}", file.size, self.tree.file_path(id, &file.name));
+            }
+        }
+    }
+
+    fn rm(&mut self, path: Option<&str>, recursive: bool) {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                println!("rm: missing operand");
+                return;
+            }
+        };
+        if matches!(path.trim_end_matches("/").rsplit("/").next(), Some(".") | Some("..")) {
+            println!("rm: refusing to remove '.' or '..' directory: {}", path);
+            return;
+        }
+        if let Some(dir) = self.tree.resolve(self.cwd, path) {
+            if !recursive {
+                println!("rm: {} is a directory, use rm -r", path);
+            }
+            else if dir == FSTree::ROOT {
+                println!("rm: refusing to remove /");
+            }
+            else {
+                if self.tree.depth_first(dir).any(|id| id == self.cwd) {
+                    self.cwd = self.tree.parent(dir).unwrap();
+                }
+                let freed = self.tree.remove_dir(dir);
+                println!("removed {}, freed {}", path, freed);
+            }
+            return;
+        }
+
+        // otherwise a file, in the current directory or the one named by the path
+        let (parent_path, name) = match path.rsplit_once("/") {
+            Some((parent, name)) => (if parent.is_empty() { "/" } else { parent }, name),
+            None => (".", path)
+        };
+        match self.tree.resolve(self.cwd, parent_path).and_then(|parent| self.tree.remove_file(parent, name)) {
+            Some(freed) => println!("removed {}, freed {}", path, freed),
+            None => println!("rm: no such file or directory: {}", path)
+        }
+    }
+}
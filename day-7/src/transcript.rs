@@ -0,0 +1,105 @@
+use std::{fs, io, path::Path};
+
+/// Xorshift generator for the shuffled traversal order
+pub struct Rng {
+    state: u64
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        let state = seed ^ 0x9E37_79B9_7F4A_7C15;
+        // a zero state would only ever produce zeros
+        Rng { state: if state == 0 { 1 } else { state } }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Fisher-Yates shuffle
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for idx in (1..items.len()).rev() {
+            items.swap(idx, self.below(idx + 1));
+        }
+    }
+}
+
+/// A directory as read from disk
+pub struct RealDir {
+    pub name: String,
+    pub files: Vec<(String, usize)>,
+    pub dirs: Vec<RealDir>
+}
+
+impl RealDir {
+    /// Read a directory and everything below it, skipping symlinks so the walk cannot loop
+    pub fn read(path: &Path) -> io::Result<RealDir> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let mut real_dir = RealDir { name, files: vec![], dirs: vec![] };
+        let mut entries = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            let metadata = entry.path().symlink_metadata()?;
+            if metadata.is_dir() {
+                match RealDir::read(&entry.path()) {
+                    Ok(sub_dir) => real_dir.dirs.push(sub_dir),
+                    Err(err) => eprintln!("skipping {}: {}", entry.path().display(), err)
+                }
+            } else if metadata.is_file() {
+                real_dir.files.push((entry.file_name().to_string_lossy().to_string(), metadata.len() as usize));
+            }
+        }
+        Ok(real_dir)
+    }
+
+    /// Total size of every file below, the size the puzzle asks for
+    pub fn tree_size(&self) -> usize {
+        self.files.iter().map(|&(_, size)| size).sum::<usize>() + self.dirs.iter().map(|dir| dir.tree_size()).sum::<usize>()
+    }
+
+    /// Absolute puzzle path and real size of this directory and everything below it
+    pub fn sizes(&self, path: &str) -> Vec<(String, usize)> {
+        let mut sizes = vec![(path.to_string(), self.tree_size())];
+        for dir in self.dirs.iter() {
+            let sub_path = match path {
+                "/" => format!("/{}", dir.name),
+                _ => format!("{}/{}", path, dir.name)
+            };
+            sizes.extend(dir.sizes(&sub_path));
+        }
+        sizes
+    }
+}
+
+/// Terminal transcript exploring the directory in the puzzle format, in a random order when given a generator
+pub fn transcript(root: &RealDir, mut rng: Option<&mut Rng>) -> Vec<String> {
+    let mut lines = vec!["$ cd /".to_string()];
+    explore(root, &mut lines, &mut rng);
+    lines
+}
+
+fn explore(dir: &RealDir, lines: &mut Vec<String>, rng: &mut Option<&mut Rng>) {
+    lines.push("$ ls".to_string());
+    let mut entries = dir.dirs.iter().map(|sub_dir| format!("dir {}", sub_dir.name))
+        .chain(dir.files.iter().map(|(name, size)| format!("{} {}", size, name))).collect::<Vec<_>>();
+    let mut visit = dir.dirs.iter().collect::<Vec<_>>();
+    if let Some(rng) = rng {
+        rng.shuffle(&mut entries);
+        rng.shuffle(&mut visit);
+    }
+    lines.extend(entries);
+    for sub_dir in visit {
+        lines.push(format!("$ cd {}", sub_dir.name));
+        explore(sub_dir, lines, rng);
+        lines.push("$ cd ..".to_string());
+    }
+}
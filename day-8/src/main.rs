@@ -1,153 +1,117 @@
-use std::{io::{self}, collections::HashMap, vec, num};
-use std::cmp;
+use std::{env, io::{self}, process};
+
+mod view;
+
+use view::{DIRECTIONS, ForestView};
 
 struct Forest {
-    trees: Vec<Vec<i8>>,
+    trees: Vec<Vec<u32>>,
     rows: usize,
     columns: usize
 }
 
+/// Separators between heights in the delimited format, which allows heights of more than one digit
+fn is_delimiter(c: char) -> bool {
+    c == ',' || c.is_whitespace()
+}
+
 impl Forest {
-    fn from_lines(lines: Vec<String>) -> Forest {
-        let char_map: Vec<Vec<char>> = lines.iter().map(|line| line.chars().collect::<Vec<char>>()).collect();
-        let mut trees: Vec<Vec<i8>> = char_map.into_iter().map(|col|col.into_iter().map(|c| c.to_string().parse::<i8>().unwrap()).collect::<Vec<i8>>()).collect();
-
-        let columns = lines[0].len();
-        let rows = lines.len();
-    
-        let mut new_forest = Forest{ trees: trees, rows: rows, columns: columns };
-        return new_forest;
-    }
+    /// Parse one digit per tree, or delimited heights when any line has a comma or space in it
+    fn from_lines(lines: Vec<String>) -> Result<Forest, String> {
+        // trailing blank lines are not part of the forest
+        let row_count = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |idx| idx + 1);
+        let delimited = lines[..row_count].iter().any(|line| line.trim().contains(is_delimiter));
 
-    fn scenic_scoring(&self) -> u32 {
-        let mut score: Vec<Vec<u32>> = vec![vec![0;self.columns];self.rows];
-        for r in 0..self.rows {
-            for c in 0..self.columns {
-                // look east
-                let mut num_trees = 0u32;
-                for east in c+1..self.columns {
-                    num_trees = num_trees + 1;
-                    if self.trees[r][c] <= self.trees[r][east] {
-                        break;
-                    }
-                }
-                score[r][c] = num_trees;
-            }
-        }
-        for r in 0..self.rows {
-            for c in 0..self.columns { // First column will always be one, so skip
-                // look west
-                let mut num_trees = 0u32;
-                for west in (0..c).rev() {
-                    num_trees = num_trees + 1;
-                    if self.trees[r][c] <= self.trees[r][west] {
-                        break;
-                    }
+        let mut trees: Vec<Vec<u32>> = vec![];
+        for (idx, line) in lines[..row_count].iter().enumerate() {
+            let line_number = idx + 1;
+            let row = match delimited {
+                true => line.split(is_delimiter).filter(|height| !height.is_empty()).map(|height| {
+                    height.parse::<u32>().map_err(|_| format!("line {}: not a height: {:?}", line_number, height))
+                }).collect::<Result<Vec<_>, _>>()?,
+                false => {
+                    // surrounding whitespace is ignored here just as when choosing the format
+                    let indent = line.chars().take_while(|c| c.is_whitespace()).count();
+                    line.trim().chars().enumerate().map(|(column, c)| {
+                        c.to_digit(10).ok_or(format!("line {}, column {}: not a height: {:?}", line_number, indent + column + 1, c))
+                    }).collect::<Result<Vec<_>, _>>()?
                 }
-                score[r][c] = score[r][c] * num_trees;
-            }
-        }
-        for c in 0..self.columns {
-            for r in 0..self.rows {            
-                // look down
-                let mut num_trees = 0u32;
-                for south in r+1..self.rows {
-                    num_trees = num_trees + 1;
-                    if self.trees[r][c] <= self.trees[south][c] {
-                        break;
-                    }
+            };
+            if let Some(first_row) = trees.first() {
+                if row.len() != first_row.len() {
+                    return Err(format!("line {}: {} trees but the first row has {}", line_number, row.len(), first_row.len()));
                 }
-                score[r][c] = score[r][c] * num_trees;
-            }
-        }
-        for c in 0..self.columns {
-            for r in 0..self.rows {            
-                // look up
-                let mut num_trees = 0u32;
-                for north in (0..r).rev() {
-                    num_trees = num_trees + 1;
-                    if self.trees[r][c] <= self.trees[north][c] {
-                        break;
-                    }
-                }
-                score[r][c] = score[r][c] * num_trees;
             }
+            trees.push(row);
         }
 
-        let largest_scenic_score = score.iter().fold(0,|acc: u32, col| {
-            let col_max = col.iter().max().unwrap().to_owned();
-            cmp::max(acc,col_max)
-        });
-        return largest_scenic_score;
+        let rows = trees.len();
+        let columns = trees.first().map_or(0, |row| row.len());
+        Ok(Forest{ trees, rows, columns })
+    }
+
+    /// Visibility and viewing distances of every tree
+    fn view(&self) -> ForestView {
+        ForestView::new(&self.trees)
+    }
+
+    fn scenic_scoring(&self) -> u64 {
+        self.view().best_score().map_or(0, |(score, _, _)| score)
     }
 
     fn find_visible(&self) -> usize {
-        let mut visible: Vec<Vec<bool>> = vec![vec![false;self.columns-2];self.rows-2];
-        // from left
-        for r in 1..self.rows-1 {
-            let mut max_height: i8 = self.trees[r][0];   // left most tree height
-            for c in 1..self.columns-1 {
-                // above direction max height and currently invisible
-                if !visible[r-1][c-1] && self.trees[r][c] > max_height {
-                    visible[r-1][c-1] = true;
-                    max_height = self.trees[r][c];
-                }
-            }
-        }
-        // from right
-        for r in 1..self.rows-1 {
-            let mut max_height: i8 = self.trees[r][self.columns-1];   // left most tree height
-            for c in (1..self.columns-1).rev() {
-                // above direction max height and currently invisible
-                if self.trees[r][c] > max_height {
-                    visible[r-1][c-1] = true;
-                    max_height = self.trees[r][c];
-                }
-            }
-        }
-        // from top
-        for c in 1..self.columns-1 {
-            let mut max_height: i8 = self.trees[0][c];   // top most tree height
-            for r in 1..self.rows-1 {
-                // above direction max height and currently invisible
-                if self.trees[r][c] > max_height {
-                    visible[r-1][c-1] = true;
-                    max_height = self.trees[r][c];
-                }
-            }
-        }
-        // from bottom
-        for c in 1..self.columns-1 {
-            let mut max_height: i8 = self.trees[self.rows-1][c];   // top most tree height
-            for r in (1..self.rows-1).rev() {
-                // above direction max height and currently invisible
-                if self.trees[r][c] > max_height {
-                    visible[r-1][c-1] = true;
-                    max_height = self.trees[r][c];
-                }
-            }
+        self.view().visible_count()
+    }
+}
+
+/// Print the per-tree matrices: visibility, viewing distance in each direction and scenic score
+fn print_view(forest: &Forest) {
+    let view = forest.view();
+    println!("Forest {}x{}\r\n{}", forest.rows, forest.columns, "-".repeat(10));
+
+    println!("Edges each tree is visible from, '.' for hidden trees");
+    for (r, row) in view.visible.iter().enumerate() {
+        println!("{}", row.iter().enumerate().map(|(c, &v)| match v {
+            true => char::from_digit(view.visible_from.iter().filter(|from| from[r][c]).count() as u32, 10).unwrap(),
+            false => '.'
+        }).collect::<String>());
+    }
+
+    let width = view.score.iter().flatten().max().map_or(1, |score| score.to_string().len());
+    for (dir_idx, direction) in DIRECTIONS.iter().enumerate() {
+        println!("\nViewing distance {:?}", direction);
+        for row in view.distance[dir_idx].iter() {
+            println!("{}", row.iter().map(|dist| format!("{:>width$}", dist)).collect::<Vec<_>>().join(" "));
         }
+    }
 
-        let num_visible_trees = visible.into_iter().fold(0,|acc,col| {
-            acc + col.iter().fold(0, |acc, &v| {
-                if v {
-                    acc + 1
-                }
-                else {
-                    acc
-                }
-            })
-        }) + (self.rows * 2) + (self.columns - 2) * 2;
-        
-        return num_visible_trees;
+    println!("\nScenic score");
+    for row in view.score.iter() {
+        println!("{}", row.iter().map(|score| format!("{:>width$}", score)).collect::<Vec<_>>().join(" "));
     }
-}
 
+    println!("\nVisible: {}", view.visible_count());
+    if let Some((score, r, c)) = view.best_score() {
+        println!("Best scenic score: {} at row {}, column {}", score, r + 1, c + 1);
+    }
+}
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
     let stdin = io::stdin();
-    let lines: Vec<String> = stdin.lines().flatten().collect();
-    let mut forest = Forest::from_lines(lines);
+    let lines: Vec<String> = stdin.lines().map_while(Result::ok).collect();
+    let forest = match Forest::from_lines(lines) {
+        Ok(forest) => forest,
+        Err(err) => {
+            eprintln!("bad forest: {}", err);
+            process::exit(1);
+        }
+    };
+
+    if args.first().map(|s| s.as_str()) == Some("view") {
+        print_view(&forest);
+        return;
+    }
 
     println!("Part 1\r\n{}", "-".repeat(10));
     println!("Number of visible trees: {}\r\n", forest.find_visible());
@@ -156,3 +120,41 @@ fn main() {
     println!("Largest scenic score: {}\r\n", forest.scenic_scoring());
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(lines: &[&str]) -> Result<Forest, String> {
+        Forest::from_lines(lines.iter().map(|line| line.to_string()).collect())
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        let forest = parse(&["30373  ", "25512", "  65332", ""]).unwrap();
+        assert_eq!((forest.rows, forest.columns), (3, 5));
+        assert_eq!(forest.trees[2], [6, 5, 3, 3, 2]);
+    }
+
+    #[test]
+    fn bad_input_is_reported() {
+        assert_eq!(parse(&["123", "12"]).err().unwrap(), "line 2: 2 trees but the first row has 3");
+        assert_eq!(parse(&[" 1a3"]).err().unwrap(), "line 1, column 3: not a height: 'a'");
+    }
+
+    #[test]
+    fn empty_and_thin_forests() {
+        let empty = parse(&[]).unwrap();
+        assert_eq!((empty.find_visible(), empty.scenic_scoring()), (0, 0));
+        let row = parse(&["12321"]).unwrap();
+        assert_eq!((row.find_visible(), row.scenic_scoring()), (5, 0));
+        let column = parse(&["1", "2", "3"]).unwrap();
+        assert_eq!((column.find_visible(), column.scenic_scoring()), (3, 0));
+    }
+
+    #[test]
+    fn delimited_heights() {
+        let forest = parse(&["12,5,100", "3 4 7"]).unwrap();
+        assert_eq!(forest.trees, [vec![12, 5, 100], vec![3, 4, 7]]);
+    }
+}
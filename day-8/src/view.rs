@@ -0,0 +1,146 @@
+/// Direction a tree looks in, and the edge it can be seen from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Direction {
+    North,
+    East,
+    South,
+    West
+}
+
+pub const DIRECTIONS: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
+
+impl Direction {
+    /// Lines of cells running away from the edge in this direction, so looking this way means looking back along the line
+    fn lines(self, rows: usize, columns: usize) -> Vec<Vec<(usize, usize)>> {
+        match self {
+            Direction::North => (0..columns).map(|c| (0..rows).map(|r| (r, c)).collect()).collect(),
+            Direction::South => (0..columns).map(|c| (0..rows).rev().map(|r| (r, c)).collect()).collect(),
+            Direction::West => (0..rows).map(|r| (0..columns).map(|c| (r, c)).collect()).collect(),
+            Direction::East => (0..rows).map(|r| (0..columns).rev().map(|c| (r, c)).collect()).collect()
+        }
+    }
+}
+
+/// Per-tree results of looking out from every tree, indexed `[row][column]`
+pub struct ForestView {
+    /// Visible from at least one edge
+    pub visible: Vec<Vec<bool>>,
+    /// Visible from the edge in each direction, in `DIRECTIONS` order
+    pub visible_from: [Vec<Vec<bool>>; 4],
+    /// Trees seen before the view is blocked or the edge reached, in `DIRECTIONS` order
+    pub distance: [Vec<Vec<u32>>; 4],
+    /// Product of the four viewing distances
+    pub score: Vec<Vec<u64>>
+}
+
+impl ForestView {
+    /// Look in every direction from every tree in O(rows * columns)
+    pub fn new<T: Ord + Copy>(trees: &[Vec<T>]) -> ForestView {
+        let rows = trees.len();
+        let columns = trees.first().map_or(0, |row| row.len());
+        let mut visible_from: [Vec<Vec<bool>>; 4] = Default::default();
+        let mut distance: [Vec<Vec<u32>>; 4] = Default::default();
+
+        for (dir_idx, direction) in DIRECTIONS.iter().enumerate() {
+            visible_from[dir_idx] = vec![vec![false; columns]; rows];
+            distance[dir_idx] = vec![vec![0; columns]; rows];
+            for line in direction.lines(rows, columns) {
+                // positions along the line of trees not yet blocked by a later one at least as tall, heights strictly decreasing
+                let mut stack: Vec<usize> = vec![];
+                for (pos, &(r, c)) in line.iter().enumerate() {
+                    let height = trees[r][c];
+                    while stack.last().is_some_and(|&top| { let (tr, tc) = line[top]; trees[tr][tc] < height }) {
+                        stack.pop();
+                    }
+                    match stack.last() {
+                        Some(&blocker) => distance[dir_idx][r][c] = (pos - blocker) as u32,
+                        None => {
+                            distance[dir_idx][r][c] = pos as u32;
+                            visible_from[dir_idx][r][c] = true;
+                        }
+                    }
+                    stack.push(pos);
+                }
+            }
+        }
+
+        let visible = (0..rows).map(|r| (0..columns).map(|c| visible_from.iter().any(|from| from[r][c])).collect()).collect();
+        let score = (0..rows).map(|r| (0..columns).map(|c| distance.iter().map(|dist| dist[r][c] as u64).product()).collect()).collect();
+        ForestView { visible, visible_from, distance, score }
+    }
+
+    pub fn visible_count(&self) -> usize {
+        self.visible.iter().map(|row| row.iter().filter(|&&v| v).count()).sum()
+    }
+
+    /// Highest scenic score and the tree that has it, first in reading order on ties
+    pub fn best_score(&self) -> Option<(u64, usize, usize)> {
+        let mut best: Option<(u64, usize, usize)> = None;
+        for (r, row) in self.score.iter().enumerate() {
+            for (c, &score) in row.iter().enumerate() {
+                if best.is_none_or(|(best_score, _, _)| score > best_score) {
+                    best = Some((score, r, c));
+                }
+            }
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Vec<u32>> {
+        ["30373", "25512", "65332", "33549", "35390"].iter()
+            .map(|row| row.chars().map(|c| c.to_digit(10).unwrap()).collect())
+            .collect()
+    }
+
+    /// Walk outward from one tree the slow way, counting trees until one at least as tall
+    fn walk(trees: &[Vec<u32>], r: usize, c: usize, dr: isize, dc: isize) -> (u32, bool) {
+        let (mut row, mut column, mut seen) = (r as isize + dr, c as isize + dc, 0);
+        while row >= 0 && column >= 0 && (row as usize) < trees.len() && (column as usize) < trees[0].len() {
+            seen += 1;
+            if trees[row as usize][column as usize] >= trees[r][c] {
+                return (seen, false);
+            }
+            row += dr;
+            column += dc;
+        }
+        (seen, true)
+    }
+
+    #[test]
+    fn sample_forest() {
+        let view = ForestView::new(&sample());
+        assert_eq!(view.visible_count(), 21);
+        assert_eq!(view.best_score(), Some((8, 3, 2)));
+
+        // the middle 5 in the second row sees 1 up, 2 right, 2 down and 1 left
+        let north = DIRECTIONS.iter().position(|&d| d == Direction::North).unwrap();
+        let east = DIRECTIONS.iter().position(|&d| d == Direction::East).unwrap();
+        let south = DIRECTIONS.iter().position(|&d| d == Direction::South).unwrap();
+        let west = DIRECTIONS.iter().position(|&d| d == Direction::West).unwrap();
+        assert_eq!([view.distance[north][1][2], view.distance[east][1][2], view.distance[south][1][2], view.distance[west][1][2]], [1, 2, 2, 1]);
+        assert_eq!(view.score[1][2], 4);
+        // the 5 in the fourth row sees 2 up, 2 right, 1 down and 2 left
+        assert_eq!([view.distance[north][3][2], view.distance[east][3][2], view.distance[south][3][2], view.distance[west][3][2]], [2, 2, 1, 2]);
+        assert!(!view.visible[1][3] && view.visible_from[west][1][1] && !view.visible_from[east][1][1]);
+    }
+
+    #[test]
+    fn matches_walking_out_from_every_tree() {
+        let trees = sample();
+        let view = ForestView::new(&trees);
+        for r in 0..trees.len() {
+            for c in 0..trees[0].len() {
+                for (dir_idx, (dr, dc)) in [(-1, 0), (0, 1), (1, 0), (0, -1)].into_iter().enumerate() {
+                    let (distance, visible) = walk(&trees, r, c, dr, dc);
+                    assert_eq!(view.distance[dir_idx][r][c], distance, "{:?} from row {} column {}", DIRECTIONS[dir_idx], r, c);
+                    assert_eq!(view.visible_from[dir_idx][r][c], visible, "{:?} from row {} column {}", DIRECTIONS[dir_idx], r, c);
+                }
+            }
+        }
+    }
+}